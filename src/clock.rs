@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+/// Game time, which only moves while the game isn't paused. Systems that animate or wait, like
+/// the TweenSystem and TimerSystem, are given its delta so they freeze under the pause menu.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GameClock {
+    /// Game time since the clock started, not counting time paused.
+    elapsed: Duration,
+    /// How much game time passed in the last tick.
+    delta: Duration,
+    paused: bool,
+}
+
+impl GameClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move the clock forward by the real time since the last tick, like the time between
+    /// frames. Nothing passes while paused.
+    pub fn tick(&mut self, real_delta: Duration) {
+        self.delta = if self.paused {
+            Duration::ZERO
+        } else {
+            real_delta
+        };
+        self.elapsed += self.delta;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The game time that passed in the last tick, zero while paused.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+}
@@ -4,6 +4,7 @@ use self::component::transform::TransformComponent;
 use std::cell::{RefCell, RefMut};
 
 // World to store component vectors and entity count.
+#[derive(Default)]
 pub struct World {
     entities_count: usize,
     component_vecs: Vec<Box<dyn ComponentVec>>,
@@ -28,6 +29,14 @@ impl World {
         entity_id
     }
 
+    /// Remove every component from an entity, like when leaving the screen it's on. Its id
+    /// isn't given to a new entity.
+    pub fn remove_entity(&mut self, entity: usize) {
+        for component_vec in self.component_vecs.iter_mut() {
+            component_vec.set_none(entity);
+        }
+    }
+
     // ComponentType must be static to support downcasting Any -> ComponentType
     pub fn add_component_to_entity<ComponentType: 'static>(
         &mut self,
@@ -59,7 +68,7 @@ impl World {
 
     pub fn borrow_component_vec<ComponentType: 'static>(
         &self,
-    ) -> Option<RefMut<Vec<Option<ComponentType>>>> {
+    ) -> Option<RefMut<'_, Vec<Option<ComponentType>>>> {
         for component_vec in self.component_vecs.iter() {
             if let Some(component_vec) = component_vec
                 .as_any()
@@ -99,6 +108,7 @@ trait ComponentVec {
     // at that index the vector contains None. Every ComponentVec type must support
     // push_none.
     fn push_none(&mut self);
+    fn set_none(&mut self, entity: usize);
 }
 
 // Casting as Any requires T to be static. Casting as Any supports downcasting
@@ -111,7 +121,34 @@ impl<T: 'static> ComponentVec for RefCell<Vec<Option<T>>> {
         self.get_mut().push(None)
     }
 
+    fn set_none(&mut self, entity: usize) {
+        self.get_mut()[entity] = None
+    }
+
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self as &mut dyn std::any::Any
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_removed_entity_has_no_components() {
+        let mut world = World::new();
+        let removed = world.new_entity();
+        let kept = world.new_entity();
+        world.add_component_to_entity(removed, 1u32);
+        world.add_component_to_entity(kept, 2u32);
+        world.remove_entity(removed);
+        assert!(world
+            .borrow_component::<TransformComponent>(removed)
+            .is_none());
+        assert!(world.borrow_component::<u32>(removed).is_none());
+        assert!(world.borrow_component::<TransformComponent>(kept).is_some());
+        assert_eq!(world.borrow_component::<u32>(kept), Some(&mut 2));
+        // Ids aren't reused.
+        assert_eq!(world.new_entity(), 2);
+    }
+}
@@ -1,2 +1,8 @@
+#[cfg(feature = "audio")]
+pub mod audio_source;
+pub mod render_layer;
+pub mod sprite;
 pub mod text;
+pub mod timer;
 pub mod transform;
+pub mod tween;
@@ -0,0 +1,26 @@
+use crate::ecs::system::audio::SoundHandle;
+
+/// A sound an entity plays when something happens to it, like a pad's tone when it's pressed
+/// or lights up. Call trigger and the AudioSystem plays it on its next update.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AudioSourceComponent {
+    pub sound: SoundHandle,
+    /// From 0 to 1, scaled by the AudioSystem's volume.
+    pub volume: f32,
+    /// Whether to play the sound on the next update.
+    pub triggered: bool,
+}
+
+impl AudioSourceComponent {
+    pub fn new(sound: SoundHandle) -> Self {
+        AudioSourceComponent {
+            sound,
+            volume: 1.0,
+            triggered: false,
+        }
+    }
+
+    pub fn trigger(&mut self) {
+        self.triggered = true;
+    }
+}
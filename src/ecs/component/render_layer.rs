@@ -0,0 +1,15 @@
+/// Which layer a drawable component is drawn in. Higher layers are drawn over lower ones, and
+/// drawables in the same layer are drawn in entity order. The named layers leave room for
+/// layers in between, like RenderLayer(RenderLayer::UI.0 - 1).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RenderLayer(pub i32);
+
+impl RenderLayer {
+    pub const BACKGROUND: RenderLayer = RenderLayer(-100);
+    /// Where sprites go by default, like the simon pads.
+    pub const GAMEPLAY: RenderLayer = RenderLayer(0);
+    /// Where text goes by default, so it's over gameplay.
+    pub const UI: RenderLayer = RenderLayer(100);
+    /// Over everything else, like a pause menu.
+    pub const OVERLAY: RenderLayer = RenderLayer(200);
+}
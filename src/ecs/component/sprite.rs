@@ -0,0 +1,33 @@
+use crate::ecs::component::render_layer::RenderLayer;
+use crate::ecs::system::render::TextureHandle;
+
+/// Draws a shape filling the entity's transform, like a Simon pad, a button, or a background.
+pub struct SpriteComponent {
+    pub shape: Shape,
+    /// Multiplied with the texture's colour. Without a texture the shape is filled with it.
+    pub colour: [f32; 4],
+    /// An image stretched across the shape, from the Renderer's textures.
+    pub texture: Option<TextureHandle>,
+    pub layer: RenderLayer,
+}
+
+impl Default for SpriteComponent {
+    fn default() -> Self {
+        SpriteComponent {
+            shape: Shape::default(),
+            colour: [1.0, 1.0, 1.0, 1.0],
+            texture: None,
+            layer: RenderLayer::GAMEPLAY,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Shape {
+    #[default]
+    Rectangle,
+    /// A rectangle with corners rounded by a radius in logical pixels.
+    RoundedRectangle { radius: f32 },
+    /// The biggest circle that fits in the transform, centered in it.
+    Circle,
+}
@@ -1,9 +1,128 @@
+use crate::ecs::component::render_layer::RenderLayer;
+use crate::ecs::component::transform::Position;
+use crate::ecs::system::text::font::FontHandle;
+
 pub struct TextComponent {
     pub text: String,
+    /// When set, the LocalizationSystem replaces the text with this message in the current
+    /// locale.
+    pub message: Option<TextMessage>,
     pub alignment: TextAlignment,
+    /// Where the text sits inside the height of its transform.
+    pub vertical_alignment: VerticalAlignment,
+    /// Which font in the TextSystem's font registry to draw with.
+    pub font: FontHandle,
+    /// Lines past this many aren't drawn. None draws every line.
+    pub max_lines: Option<usize>,
+    /// What to do with text cut off by max_lines.
+    pub overflow: TextOverflow,
+    pub colour: [f32; 4],
+    pub effects: TextEffects,
+    pub layer: RenderLayer,
+}
+
+impl Default for TextComponent {
+    fn default() -> Self {
+        TextComponent {
+            text: String::new(),
+            message: None,
+            alignment: TextAlignment::default(),
+            vertical_alignment: VerticalAlignment::default(),
+            font: FontHandle::default(),
+            max_lines: None,
+            overflow: TextOverflow::default(),
+            colour: [0.0, 0.0, 0.0, 1.0],
+            effects: TextEffects::default(),
+            layer: RenderLayer::UI,
+        }
+    }
+}
+
+/// A key into the localization catalogs, and the arguments to fill into its message.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextMessage {
+    pub key: String,
+    pub args: Vec<(String, String)>,
+}
+
+impl TextMessage {
+    pub fn new(key: &str) -> Self {
+        TextMessage {
+            key: key.to_string(),
+            args: Vec::new(),
+        }
+    }
+
+    /// Set an argument, like the score in "Score: {score}".
+    pub fn arg(mut self, name: &str, value: impl ToString) -> Self {
+        self.set_arg(name, value);
+        self
+    }
+
+    pub fn set_arg(&mut self, name: &str, value: impl ToString) {
+        let value = value.to_string();
+        match self.args.iter_mut().find(|(arg_name, _)| arg_name == name) {
+            Some((_, old_value)) => *old_value = value,
+            None => self.args.push((name.to_string(), value)),
+        }
+    }
+}
+
+/// Extra ways to draw text so it stays readable over busy or flashing backgrounds.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct TextEffects {
+    pub rendering: GlyphRendering,
+    /// Only drawn with GlyphRendering::Sdf.
+    pub outline: Option<TextOutline>,
+    pub shadow: Option<TextShadow>,
 }
 
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum GlyphRendering {
+    /// Draw glyphs from bitmaps rasterized at the text's size.
+    #[default]
+    Coverage,
+    /// Draw glyphs from signed distance fields, which stay crisp at any size and can be outlined.
+    Sdf,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextOutline {
+    /// In logical pixels. Outlines wider than a few pixels are cut off.
+    pub width: f32,
+    pub colour: [f32; 4],
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextShadow {
+    /// How far the shadow is moved from the text, in logical pixels.
+    pub offset: Position,
+    pub colour: [f32; 4],
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub enum TextAlignment {
+    #[default]
     Left,
     Center,
+    Right,
+    /// Stretch the spaces between words so every line but the last fills the transform's width.
+    Justify,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum VerticalAlignment {
+    #[default]
+    Top,
+    Middle,
+    Bottom,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum TextOverflow {
+    /// Drop the text that doesn't fit.
+    #[default]
+    Clip,
+    /// End the last line with "…" to show there's more text.
+    Ellipsis,
 }
@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+/// Timers that fire an event of type E when they run out, like the steps of playing back a
+/// sequence. There's one component vec per event type, so an entity can have timers firing
+/// different kinds of events. The TimerSystem removes one-shot timers after they fire.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimerComponent<E> {
+    pub timers: Vec<Timer<E>>,
+}
+
+impl<E> TimerComponent<E> {
+    pub fn new(timer: Timer<E>) -> Self {
+        TimerComponent {
+            timers: vec![timer],
+        }
+    }
+}
+
+impl<E> Default for TimerComponent<E> {
+    fn default() -> Self {
+        TimerComponent { timers: Vec::new() }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timer<E> {
+    /// How long until the timer fires, and between fires when repeating.
+    pub duration: Duration,
+    pub repeating: bool,
+    /// Given back by the TimerSystem each time the timer fires.
+    pub event: E,
+    paused: bool,
+    /// Time run since the timer started or last fired.
+    elapsed: Duration,
+    /// Whether a one-shot timer has fired.
+    finished: bool,
+}
+
+impl<E> Timer<E> {
+    /// A timer that fires once after a duration.
+    pub fn once(duration: Duration, event: E) -> Self {
+        Timer {
+            duration,
+            repeating: false,
+            event,
+            paused: false,
+            elapsed: Duration::ZERO,
+            finished: false,
+        }
+    }
+
+    /// A timer that fires every duration until it's removed.
+    pub fn repeating(duration: Duration, event: E) -> Self {
+        Timer {
+            repeating: true,
+            ..Timer::once(duration, event)
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Whether a one-shot timer has fired. Repeating timers never finish.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Time until the timer next fires.
+    pub fn remaining(&self) -> Duration {
+        self.duration.saturating_sub(self.elapsed)
+    }
+
+    /// Start counting down again from the full duration.
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+        self.finished = false;
+    }
+
+    /// Run the timer forward, unless it's paused or finished. Returns how many times it fired,
+    /// which can be more than once for a repeating timer given a long delta.
+    pub fn tick(&mut self, delta: Duration) -> u32 {
+        if self.paused || self.finished {
+            return 0;
+        }
+        self.elapsed += delta;
+        if self.elapsed < self.duration {
+            return 0;
+        }
+        if !self.repeating {
+            self.elapsed = self.duration;
+            self.finished = true;
+            return 1;
+        }
+        if self.duration.is_zero() {
+            // Fire once a tick instead of forever.
+            self.elapsed = Duration::ZERO;
+            return 1;
+        }
+        let fires = self.elapsed.as_nanos() / self.duration.as_nanos();
+        self.elapsed =
+            Duration::from_nanos((self.elapsed.as_nanos() % self.duration.as_nanos()) as u64);
+        fires as u32
+    }
+}
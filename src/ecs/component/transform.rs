@@ -1,13 +1,17 @@
 use glium::Display;
 
+/// Transforms are positioned and sized in logical pixels, so they look the same on regular and
+/// retina displays. Systems convert them to physical pixels with a Viewport when drawing.
 pub struct TransformComponent {
     /// The position of a transform from its anchor.
     pub position: Position,
     pub anchor: Anchor,
     pub width: f32,
+    /// How tall sprites are drawn, and the height text is vertically aligned inside.
+    pub height: f32,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Position {
     pub x: f32,
     pub y: f32,
@@ -18,25 +22,66 @@ pub enum Anchor {
     TopMiddle,
 }
 
+/// The window's size in logical pixels, and how many physical pixels make up one logical pixel.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Viewport {
+    pub width: f32,
+    pub height: f32,
+    pub scale_factor: f32,
+}
+
+impl Viewport {
+    /// Read the viewport from the window. The scale factor is read every time, so it's
+    /// correct right after the window moves to a monitor with a different dpi.
+    pub fn from_display(display: &Display) -> Self {
+        let gl_window = display.gl_window();
+        let window = gl_window.window();
+        let scale_factor = window.scale_factor();
+        let size = window.inner_size().to_logical::<f32>(scale_factor);
+        Viewport {
+            width: size.width,
+            height: size.height,
+            scale_factor: scale_factor as f32,
+        }
+    }
+
+    /// Convert a position in logical pixels to physical pixels.
+    pub fn to_physical(&self, position: Position) -> Position {
+        Position {
+            x: position.x * self.scale_factor,
+            y: position.y * self.scale_factor,
+        }
+    }
+
+    /// Convert a position in physical pixels, where the top left is (0, 0), to opengl's
+    /// coordinate system, where the top left is (-1, 1) and the bottom right is (1, -1).
+    pub fn to_gl(&self, position: Position) -> [f32; 2] {
+        let physical_width = self.width * self.scale_factor;
+        let physical_height = self.height * self.scale_factor;
+        [
+            (position.x / physical_width - 0.5) * 2.0,
+            (1.0 - position.y / physical_height - 0.5) * 2.0,
+        ]
+    }
+}
+
 impl TransformComponent {
     pub fn new() -> Self {
         let position = Position { x: 0.0, y: 0.0 };
         TransformComponent {
             position,
             width: 100.0,
+            height: 100.0,
             anchor: Anchor::TopLeft,
         }
     }
 
-    /// The position of a transform from the top left corner of the screen.
-    // I don't think I like having to pass the display as a parameter, I don't think that should
-    // be required to know the absolute position.
-    pub fn absolute_position(&self, display: &Display) -> Position {
+    /// The position of a transform from the top left corner of the screen, in logical pixels.
+    pub fn absolute_position(&self, viewport: &Viewport) -> Position {
         match self.anchor {
             Anchor::TopMiddle => {
-                let window_width = display.gl_window().window().inner_size().width;
                 let anchor_position = Position {
-                    x: (window_width as f32 / 2.0),
+                    x: (viewport.width / 2.0),
                     y: 0.0,
                 };
                 Position {
@@ -48,3 +93,9 @@ impl TransformComponent {
         }
     }
 }
+
+impl Default for TransformComponent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,245 @@
+use std::time::Duration;
+
+use crate::ecs::component::transform::Position;
+
+/// Animates an entity's transform, sprite, or text over time, like a pad lighting up or text
+/// fading in. The TweenSystem plays each tween and removes it once it's finished.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TweenComponent {
+    pub tweens: Vec<Tween>,
+}
+
+impl TweenComponent {
+    pub fn new(tween: Tween) -> Self {
+        TweenComponent {
+            tweens: vec![tween],
+        }
+    }
+}
+
+/// One value animated from a start to an end.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tween {
+    pub property: TweenProperty,
+    pub easing: Easing,
+    /// How long one play from start to end takes.
+    pub duration: Duration,
+    /// How long to wait before the first play. The value isn't changed while waiting.
+    pub delay: Duration,
+    pub looping: Looping,
+    /// Play every other loop backwards, like a pad lighting up and dimming again.
+    pub yoyo: bool,
+    /// Given back in the TweenEvents of this tween, so they can be told apart.
+    pub label: &'static str,
+    /// How long the tween has been playing for, including the delay.
+    elapsed: Duration,
+}
+
+impl Tween {
+    pub fn new(property: TweenProperty, duration: Duration) -> Self {
+        Tween {
+            property,
+            easing: Easing::default(),
+            duration,
+            delay: Duration::ZERO,
+            looping: Looping::default(),
+            yoyo: false,
+            label: "",
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    pub fn easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn looping(mut self, looping: Looping) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    pub fn yoyo(mut self) -> Self {
+        self.yoyo = true;
+        self
+    }
+
+    pub fn label(mut self, label: &'static str) -> Self {
+        self.label = label;
+        self
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Move the tween forward in time. Returns the progress to set its property at, from 0 at
+    /// the start to 1 at the end with easing applied, or None while it's still delayed, and how
+    /// many plays finished during the step.
+    pub fn advance(&mut self, delta: Duration) -> (Option<f32>, u32) {
+        // A tween that plays no times never sets its property.
+        if self.looping.plays() == Some(0) {
+            return (None, 0);
+        }
+        let plays_before = self.finished_plays();
+        self.elapsed += delta;
+        let plays_after = self.finished_plays();
+        let Some(playing) = self.elapsed.checked_sub(self.delay) else {
+            return (None, 0);
+        };
+
+        let play = if self.is_finished() {
+            // Stay on the end of the last play.
+            plays_after.saturating_sub(1)
+        } else {
+            plays_after
+        };
+        let progress = if self.is_finished() || self.duration.is_zero() {
+            1.0
+        } else {
+            let into_play = playing.as_nanos() % self.duration.as_nanos();
+            (into_play as f64 / self.duration.as_nanos() as f64) as f32
+        };
+        let progress = if self.yoyo && play % 2 == 1 {
+            1.0 - progress
+        } else {
+            progress
+        };
+        (
+            Some(self.easing.apply(progress)),
+            plays_after - plays_before,
+        )
+    }
+
+    /// Whether every play has finished. Tweens that loop forever never finish.
+    pub fn is_finished(&self) -> bool {
+        match self.looping.plays() {
+            Some(plays) => self.finished_plays() >= plays,
+            None => false,
+        }
+    }
+
+    /// How many times the tween has played from start to end, up to how many times it plays.
+    fn finished_plays(&self) -> u32 {
+        let Some(playing) = self.elapsed.checked_sub(self.delay) else {
+            return 0;
+        };
+        if self.duration.is_zero() {
+            // Finished as soon as it starts, unless it would loop forever.
+            return self.looping.plays().unwrap_or(0);
+        }
+        let plays = playing.as_nanos() / self.duration.as_nanos();
+        let max_plays = self.looping.plays().unwrap_or(u32::MAX);
+        plays.min(max_plays as u128) as u32
+    }
+}
+
+/// What a tween animates, and the values it goes between.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TweenProperty {
+    Position { from: Position, to: Position },
+    Width { from: f32, to: f32 },
+    SpriteColour { from: [f32; 4], to: [f32; 4] },
+    TextColour { from: [f32; 4], to: [f32; 4] },
+}
+
+/// How many times a tween plays.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Looping {
+    #[default]
+    Once,
+    /// Play this many times. With 0 the tween finishes on its first update without playing.
+    Times(u32),
+    Forever,
+}
+
+impl Looping {
+    /// How many plays there are, or None for forever.
+    pub fn plays(&self) -> Option<u32> {
+        match self {
+            Looping::Once => Some(1),
+            Looping::Times(plays) => Some(*plays),
+            Looping::Forever => None,
+        }
+    }
+}
+
+/// The curve a tween follows from start to end.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    /// Starts slow and speeds up.
+    EaseIn,
+    /// Starts fast and slows down.
+    EaseOut,
+    /// Starts and ends slow.
+    EaseInOut,
+    /// Jumps to the end halfway through, like a light switching on.
+    Step,
+}
+
+impl Easing {
+    /// Map progress from 0 to 1 along the curve.
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::Step => {
+                if t < 0.5 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EASINGS: [Easing; 5] = [
+        Easing::Linear,
+        Easing::EaseIn,
+        Easing::EaseOut,
+        Easing::EaseInOut,
+        Easing::Step,
+    ];
+
+    #[test]
+    fn every_easing_starts_at_0_and_ends_at_1() {
+        for easing in EASINGS {
+            assert_eq!(easing.apply(0.0), 0.0, "{:?}", easing);
+            assert_eq!(easing.apply(1.0), 1.0, "{:?}", easing);
+            // Progress past either end is clamped.
+            assert_eq!(easing.apply(-0.5), 0.0, "{:?}", easing);
+            assert_eq!(easing.apply(1.5), 1.0, "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn easings_bend_the_middle() {
+        assert_eq!(Easing::Linear.apply(0.25), 0.25);
+        assert_eq!(Easing::EaseIn.apply(0.5), 0.125);
+        assert_eq!(Easing::EaseOut.apply(0.5), 0.875);
+        assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+        assert_eq!(Easing::Step.apply(0.49), 0.0);
+        assert_eq!(Easing::Step.apply(0.5), 1.0);
+    }
+}
@@ -1,2 +1,9 @@
+#[cfg(feature = "audio")]
+pub mod audio;
 pub mod input;
+pub mod localization;
+pub mod render;
+pub mod sprite;
 pub mod text;
+pub mod timer;
+pub mod tween;
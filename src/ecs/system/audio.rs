@@ -0,0 +1,372 @@
+#[cfg(feature = "audio-device")]
+pub mod device;
+pub mod null;
+pub mod sound;
+pub mod synth;
+
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use self::sound::Sound;
+use self::synth::Patch;
+use crate::ecs::component::audio_source::AudioSourceComponent;
+use crate::ecs::World;
+
+#[derive(Debug)]
+pub enum AudioError {
+    /// The sound file couldn't be read from disk.
+    Io(std::io::Error),
+    /// The file isn't a wav file the decoder understands.
+    Wav(hound::Error),
+    /// The file isn't an ogg vorbis file the decoder understands.
+    Ogg(lewton::VorbisError),
+    /// The file's extension isn't wav or ogg.
+    UnsupportedFile(String),
+    /// There's no sound device to play on.
+    NoDevice,
+    #[cfg(feature = "audio-device")]
+    DeviceConfig(cpal::DefaultStreamConfigError),
+    /// The device only takes samples in a format that isn't supported.
+    #[cfg(feature = "audio-device")]
+    UnsupportedSampleFormat(cpal::SampleFormat),
+    #[cfg(feature = "audio-device")]
+    BuildStream(cpal::BuildStreamError),
+    #[cfg(feature = "audio-device")]
+    PlayStream(cpal::PlayStreamError),
+}
+
+impl fmt::Display for AudioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioError::Io(err) => write!(f, "could not read sound file: {}", err),
+            AudioError::Wav(err) => write!(f, "could not decode wav file: {}", err),
+            AudioError::Ogg(err) => write!(f, "could not decode ogg file: {}", err),
+            AudioError::UnsupportedFile(path) => {
+                write!(f, "{} is not a wav or ogg file", path)
+            }
+            AudioError::NoDevice => write!(f, "there's no sound device to play on"),
+            #[cfg(feature = "audio-device")]
+            AudioError::DeviceConfig(err) => {
+                write!(f, "could not read the sound device's config: {}", err)
+            }
+            #[cfg(feature = "audio-device")]
+            AudioError::UnsupportedSampleFormat(format) => {
+                write!(
+                    f,
+                    "the sound device's sample format {} isn't supported",
+                    format
+                )
+            }
+            #[cfg(feature = "audio-device")]
+            AudioError::BuildStream(err) => write!(f, "could not open the sound device: {}", err),
+            #[cfg(feature = "audio-device")]
+            AudioError::PlayStream(err) => write!(f, "could not start the sound device: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for AudioError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AudioError::Io(err) => Some(err),
+            AudioError::Wav(err) => Some(err),
+            AudioError::Ogg(err) => Some(err),
+            AudioError::UnsupportedFile(_) | AudioError::NoDevice => None,
+            #[cfg(feature = "audio-device")]
+            AudioError::DeviceConfig(err) => Some(err),
+            #[cfg(feature = "audio-device")]
+            AudioError::UnsupportedSampleFormat(_) => None,
+            #[cfg(feature = "audio-device")]
+            AudioError::BuildStream(err) => Some(err),
+            #[cfg(feature = "audio-device")]
+            AudioError::PlayStream(err) => Some(err),
+        }
+    }
+}
+
+impl From<std::io::Error> for AudioError {
+    fn from(err: std::io::Error) -> Self {
+        AudioError::Io(err)
+    }
+}
+
+impl From<hound::Error> for AudioError {
+    fn from(err: hound::Error) -> Self {
+        AudioError::Wav(err)
+    }
+}
+
+impl From<lewton::VorbisError> for AudioError {
+    fn from(err: lewton::VorbisError) -> Self {
+        AudioError::Ogg(err)
+    }
+}
+
+/// Plays sounds the AudioSystem gives it, like a sound device. Sounds are mono samples at the
+/// output's sample rate, so outputs only have to mix them, and there can be a null output
+/// where there's no sound device, like on CI, or a recording output in tests.
+pub trait AudioOutput {
+    /// Samples per second sounds are played at.
+    fn sample_rate(&self) -> u32;
+
+    /// Start playing a sound over anything already playing, at a volume from 0 to 1.
+    fn play(&mut self, samples: Arc<[f32]>, volume: f32);
+
+    /// The volume everything is played at, from 0 to 1. Also changes sounds already playing.
+    fn set_volume(&mut self, volume: f32);
+
+    /// Stop every sound playing.
+    fn stop_all(&mut self);
+}
+
+/// So the output can be picked when the game starts, like a device or a null output when
+/// there's no device.
+impl<O: AudioOutput + ?Sized> AudioOutput for Box<O> {
+    fn sample_rate(&self) -> u32 {
+        (**self).sample_rate()
+    }
+
+    fn play(&mut self, samples: Arc<[f32]>, volume: f32) {
+        (**self).play(samples, volume)
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        (**self).set_volume(volume)
+    }
+
+    fn stop_all(&mut self) {
+        (**self).stop_all()
+    }
+}
+
+/// Refers to a sound loaded into an AudioSystem.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SoundHandle(usize);
+
+impl SoundHandle {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// Loads sounds and plays them through an output, for the pads' tones and sound effects.
+pub struct AudioSystem<O: AudioOutput> {
+    output: O,
+    /// Each loaded sound's samples, at the output's sample rate.
+    sounds: Vec<Arc<[f32]>>,
+    volume: f32,
+    muted: bool,
+}
+
+impl<O: AudioOutput> AudioSystem<O> {
+    pub fn new(output: O) -> Self {
+        let mut audio_system = AudioSystem {
+            output,
+            sounds: Vec::new(),
+            volume: 1.0,
+            muted: false,
+        };
+        audio_system.update_output_volume();
+        audio_system
+    }
+
+    pub fn output(&self) -> &O {
+        &self.output
+    }
+
+    pub fn output_mut(&mut self) -> &mut O {
+        &mut self.output
+    }
+
+    /// Load a sound to play later, converting it to the output's sample rate.
+    pub fn load_sound(&mut self, sound: &Sound) -> SoundHandle {
+        let samples = sound.resampled(self.output.sample_rate()).samples;
+        self.sounds.push(samples.into());
+        SoundHandle(self.sounds.len() - 1)
+    }
+
+    /// Load a wav or ogg file, picked by its extension.
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<SoundHandle, AudioError> {
+        let sound = Sound::load(path)?;
+        Ok(self.load_sound(&sound))
+    }
+
+    /// Load a note played by a synth patch, like Patch::simon_pad or Patch::failure_buzzer.
+    pub fn load_patch(&mut self, patch: &Patch, held: Duration) -> SoundHandle {
+        let sound = patch.render(held, self.output.sample_rate());
+        self.load_sound(&sound)
+    }
+
+    /// Play a loaded sound at a volume from 0 to 1, scaled by the system's volume. Nothing is
+    /// played while muted.
+    pub fn play(&mut self, sound: SoundHandle, volume: f32) {
+        if self.muted {
+            return;
+        }
+        let samples = self.sounds[sound.index()].clone();
+        self.output.play(samples, volume.clamp(0.0, 1.0));
+    }
+
+    /// Play every AudioSourceComponent that was triggered since the last update.
+    pub fn update(&mut self, world: &World) {
+        let Some(mut audio_sources) = world.borrow_component_vec::<AudioSourceComponent>() else {
+            return;
+        };
+        for audio_source in audio_sources.iter_mut().flatten() {
+            if audio_source.triggered {
+                audio_source.triggered = false;
+                self.play(audio_source.sound, audio_source.volume);
+            }
+        }
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    /// Set the volume of every sound, from 0 to 1.
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+        self.update_output_volume();
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    /// Mute or unmute. Muting stops what's playing too.
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        if muted {
+            self.output.stop_all();
+        }
+        self.update_output_volume();
+    }
+
+    fn update_output_volume(&mut self) {
+        let volume = if self.muted { 0.0 } else { self.volume };
+        self.output.set_volume(volume);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::null::{NullOutput, RecordingOutput};
+    use super::*;
+
+    fn sound(samples: Vec<f32>) -> Sound {
+        Sound {
+            samples,
+            sample_rate: 44100,
+        }
+    }
+
+    /// A world with one entity that has a source playing a sound.
+    fn world_with(audio_source: AudioSourceComponent) -> World {
+        let mut world = World::new();
+        let entity = world.new_entity();
+        world.add_component_to_entity(entity, audio_source);
+        world
+    }
+
+    fn is_triggered(world: &mut World) -> bool {
+        world
+            .borrow_component::<AudioSourceComponent>(0)
+            .unwrap()
+            .triggered
+    }
+
+    #[test]
+    fn a_triggered_source_plays_once() {
+        let mut audio_system = AudioSystem::new(RecordingOutput::default());
+        let handle = audio_system.load_sound(&sound(vec![0.1, 0.2]));
+        let mut audio_source = AudioSourceComponent::new(handle);
+        audio_source.volume = 0.5;
+        audio_source.trigger();
+        let mut world = world_with(audio_source);
+
+        audio_system.update(&world);
+        let played = audio_system.output_mut().take_played();
+        assert_eq!(played.len(), 1);
+        assert_eq!(&*played[0].samples, [0.1, 0.2]);
+        assert_eq!(played[0].volume, 0.5);
+        assert!(!is_triggered(&mut world));
+
+        audio_system.update(&world);
+        assert!(audio_system.output().played().is_empty());
+    }
+
+    #[test]
+    fn a_source_that_isnt_triggered_doesnt_play() {
+        let mut audio_system = AudioSystem::new(RecordingOutput::default());
+        let handle = audio_system.load_sound(&sound(vec![0.1]));
+        let world = world_with(AudioSourceComponent::new(handle));
+        audio_system.update(&world);
+        assert!(audio_system.output().played().is_empty());
+    }
+
+    #[test]
+    fn nothing_plays_while_muted() {
+        let mut audio_system = AudioSystem::new(RecordingOutput::default());
+        let handle = audio_system.load_sound(&sound(vec![0.1]));
+        audio_system.set_volume(0.8);
+        audio_system.set_muted(true);
+        assert_eq!(audio_system.output().volume(), 0.0);
+
+        let mut audio_source = AudioSourceComponent::new(handle);
+        audio_source.trigger();
+        let mut world = world_with(audio_source);
+        audio_system.update(&world);
+        assert!(audio_system.output().played().is_empty());
+        // The sound is skipped, not saved for later.
+        assert!(!is_triggered(&mut world));
+
+        audio_system.set_muted(false);
+        assert_eq!(audio_system.output().volume(), 0.8);
+        audio_system.play(handle, 1.0);
+        assert_eq!(audio_system.output().played().len(), 1);
+    }
+
+    #[test]
+    fn volumes_are_kept_between_0_and_1() {
+        let mut audio_system = AudioSystem::new(RecordingOutput::default());
+        let handle = audio_system.load_sound(&sound(vec![0.1]));
+        audio_system.set_volume(2.0);
+        assert_eq!(audio_system.volume(), 1.0);
+        audio_system.set_volume(0.25);
+        assert_eq!(audio_system.output().volume(), 0.25);
+
+        audio_system.play(handle, 3.0);
+        audio_system.play(handle, -1.0);
+        let volumes: Vec<f32> = audio_system
+            .output_mut()
+            .take_played()
+            .iter()
+            .map(|played| played.volume)
+            .collect();
+        assert_eq!(volumes, [1.0, 0.0]);
+    }
+
+    #[test]
+    fn sounds_are_loaded_at_the_outputs_sample_rate() {
+        let mut audio_system = AudioSystem::new(RecordingOutput::new(48000));
+        let handle = audio_system.load_sound(&Sound {
+            samples: vec![0.0; 100],
+            sample_rate: 24000,
+        });
+        audio_system.play(handle, 1.0);
+        assert_eq!(audio_system.output().played()[0].samples.len(), 200);
+    }
+
+    #[test]
+    fn the_null_output_takes_the_volume_and_plays_nothing() {
+        let mut audio_system = AudioSystem::new(NullOutput::new(22050));
+        let handle = audio_system.load_sound(&sound(vec![0.1]));
+        audio_system.set_volume(0.5);
+        audio_system.play(handle, 1.0);
+        assert_eq!(audio_system.output().sample_rate(), 22050);
+        assert_eq!(audio_system.output().volume(), 0.5);
+    }
+}
@@ -0,0 +1,95 @@
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample, Stream, StreamConfig};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use super::synth::Mixer;
+use super::{AudioError, AudioOutput};
+
+/// Plays sounds on the default output device with cpal, which mixes them on its own thread.
+pub struct DeviceOutput {
+    /// Sound stops when the stream is dropped.
+    _stream: Stream,
+    sample_rate: u32,
+    mixer: Arc<Mutex<Mixer>>,
+}
+
+impl DeviceOutput {
+    /// Open the default output device of the default host, like PulseAudio or ALSA on linux.
+    pub fn new() -> Result<Self, AudioError> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or(AudioError::NoDevice)?;
+        let supported_config = device
+            .default_output_config()
+            .map_err(AudioError::DeviceConfig)?;
+        let sample_format = supported_config.sample_format();
+        let config: StreamConfig = supported_config.into();
+        let mixer = Arc::new(Mutex::new(Mixer::new()));
+        let stream = match sample_format {
+            SampleFormat::F32 => build_stream::<f32>(&device, &config, mixer.clone()),
+            SampleFormat::I16 => build_stream::<i16>(&device, &config, mixer.clone()),
+            SampleFormat::U16 => build_stream::<u16>(&device, &config, mixer.clone()),
+            sample_format => return Err(AudioError::UnsupportedSampleFormat(sample_format)),
+        }?;
+        stream.play().map_err(AudioError::PlayStream)?;
+        Ok(DeviceOutput {
+            _stream: stream,
+            sample_rate: config.sample_rate.0,
+            mixer,
+        })
+    }
+
+    fn mixer(&self) -> MutexGuard<'_, Mixer> {
+        lock(&self.mixer)
+    }
+}
+
+/// The device's thread only mixes, so the mixer is still fine to use if it panicked.
+fn lock(mixer: &Mutex<Mixer>) -> MutexGuard<'_, Mixer> {
+    mixer
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Open a stream that fills every channel of each frame with the same mixed sample.
+fn build_stream<T: SizedSample + FromSample<f32>>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    mixer: Arc<Mutex<Mixer>>,
+) -> Result<Stream, AudioError> {
+    let channels = config.channels as usize;
+    // Kept between callbacks so mixing doesn't allocate on the device's thread.
+    let mut mixed = Vec::new();
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+                mixed.resize(data.len() / channels, 0.0);
+                lock(&mixer).mix(&mut mixed);
+                for (frame, sample) in data.chunks_mut(channels).zip(mixed.iter()) {
+                    frame.fill(T::from_sample(*sample));
+                }
+            },
+            |err| eprintln!("sound device error: {}", err),
+            None,
+        )
+        .map_err(AudioError::BuildStream)
+}
+
+impl AudioOutput for DeviceOutput {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn play(&mut self, samples: Arc<[f32]>, volume: f32) {
+        self.mixer().add(samples, volume);
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        self.mixer().volume = volume;
+    }
+
+    fn stop_all(&mut self) {
+        self.mixer().clear();
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use super::AudioOutput;
+
+/// An output that doesn't play anything. It needs no sound device, so the game can run
+/// without one, like on CI.
+pub struct NullOutput {
+    sample_rate: u32,
+    volume: f32,
+}
+
+impl NullOutput {
+    pub fn new(sample_rate: u32) -> Self {
+        NullOutput {
+            sample_rate,
+            volume: 1.0,
+        }
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+}
+
+impl Default for NullOutput {
+    fn default() -> Self {
+        Self::new(44100)
+    }
+}
+
+impl AudioOutput for NullOutput {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn play(&mut self, _samples: Arc<[f32]>, _volume: f32) {}
+
+    fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+    }
+
+    fn stop_all(&mut self) {}
+}
+
+/// A sound a RecordingOutput was asked to play.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayedSound {
+    pub samples: Arc<[f32]>,
+    pub volume: f32,
+}
+
+/// An output that doesn't play anything either, it only remembers what it was asked to play,
+/// so systems can be checked in tests by looking at what they played. Everything played is
+/// kept until it's taken, so take it often.
+pub struct RecordingOutput {
+    sample_rate: u32,
+    volume: f32,
+    played: Vec<PlayedSound>,
+}
+
+impl RecordingOutput {
+    pub fn new(sample_rate: u32) -> Self {
+        RecordingOutput {
+            sample_rate,
+            volume: 1.0,
+            played: Vec::new(),
+        }
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    pub fn played(&self) -> &[PlayedSound] {
+        &self.played
+    }
+
+    /// Take the sounds played, leaving none recorded.
+    pub fn take_played(&mut self) -> Vec<PlayedSound> {
+        std::mem::take(&mut self.played)
+    }
+}
+
+impl Default for RecordingOutput {
+    fn default() -> Self {
+        Self::new(44100)
+    }
+}
+
+impl AudioOutput for RecordingOutput {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn play(&mut self, samples: Arc<[f32]>, volume: f32) {
+        self.played.push(PlayedSound { samples, volume });
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+    }
+
+    fn stop_all(&mut self) {}
+}
@@ -0,0 +1,248 @@
+use std::f32::consts::TAU;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+use std::time::Duration;
+
+use super::AudioError;
+
+/// How long tones fade in and out, so they don't click when they start and stop.
+const TONE_FADE: Duration = Duration::from_millis(5);
+
+/// Mono samples from -1 to 1, played at a sample rate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sound {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+}
+
+impl Sound {
+    /// A sine wave at a frequency in hertz.
+    pub fn tone(frequency: f32, duration: Duration, sample_rate: u32) -> Self {
+        let sample_count = (duration.as_secs_f32() * sample_rate as f32) as usize;
+        let fade_count = (TONE_FADE.as_secs_f32() * sample_rate as f32) as usize;
+        let samples = (0..sample_count)
+            .map(|i| {
+                let time = i as f32 / sample_rate as f32;
+                let from_edge = i.min(sample_count - 1 - i);
+                let fade = (from_edge as f32 / fade_count.max(1) as f32).min(1.0);
+                (TAU * frequency * time).sin() * fade
+            })
+            .collect();
+        Sound {
+            samples,
+            sample_rate,
+        }
+    }
+
+    /// Load a wav or ogg vorbis file, picked by its extension.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, AudioError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("wav") => Sound::load_wav(path),
+            Some("ogg") => Sound::load_ogg(path),
+            _ => Err(AudioError::UnsupportedFile(path.display().to_string())),
+        }
+    }
+
+    pub fn load_wav(path: impl AsRef<Path>) -> Result<Self, AudioError> {
+        Sound::from_wav(BufReader::new(File::open(path)?))
+    }
+
+    pub fn load_ogg(path: impl AsRef<Path>) -> Result<Self, AudioError> {
+        Sound::from_ogg(BufReader::new(File::open(path)?))
+    }
+
+    /// Decode a wav file of integer or float samples. Channels are mixed down to mono.
+    pub fn from_wav(reader: impl Read) -> Result<Self, AudioError> {
+        let mut reader = hound::WavReader::new(reader)?;
+        let spec = reader.spec();
+        let interleaved = match spec.sample_format {
+            hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<Vec<_>, _>>()?,
+            hound::SampleFormat::Int => {
+                let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .samples::<i32>()
+                    .map(|sample| sample.map(|sample| sample as f32 * scale))
+                    .collect::<Result<Vec<_>, _>>()?
+            }
+        };
+        Ok(Sound {
+            samples: to_mono(&interleaved, spec.channels as usize),
+            sample_rate: spec.sample_rate,
+        })
+    }
+
+    /// Decode an ogg vorbis file. Channels are mixed down to mono.
+    pub fn from_ogg(reader: impl Read + Seek) -> Result<Self, AudioError> {
+        let mut reader = lewton::inside_ogg::OggStreamReader::new(reader)?;
+        let mut interleaved = Vec::new();
+        while let Some(packet) = reader.read_dec_packet_itl()? {
+            interleaved.extend(packet.into_iter().map(|sample| sample as f32 / 32768.0));
+        }
+        Ok(Sound {
+            samples: to_mono(&interleaved, reader.ident_hdr.audio_channels as usize),
+            sample_rate: reader.ident_hdr.audio_sample_rate,
+        })
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.samples.len() as f64 / self.sample_rate as f64)
+    }
+
+    /// The sound at another sample rate, by interpolating between samples.
+    pub fn resampled(&self, sample_rate: u32) -> Sound {
+        if sample_rate == self.sample_rate || self.samples.is_empty() {
+            return Sound {
+                samples: self.samples.clone(),
+                sample_rate,
+            };
+        }
+        let step = self.sample_rate as f64 / sample_rate as f64;
+        let sample_count = (self.samples.len() as f64 / step) as usize;
+        let samples = (0..sample_count)
+            .map(|i| {
+                let position = i as f64 * step;
+                let index = position as usize;
+                let next = self.samples.get(index + 1).unwrap_or(&self.samples[index]);
+                let t = (position - index as f64) as f32;
+                self.samples[index] + (next - self.samples[index]) * t
+            })
+            .collect();
+        Sound {
+            samples,
+            sample_rate,
+        }
+    }
+}
+
+/// Average each frame of interleaved samples into one sample.
+fn to_mono(interleaved: &[f32], channels: usize) -> Vec<f32> {
+    let channels = channels.max(1);
+    interleaved
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(
+            actual.len(),
+            expected.len(),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 0.001, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    /// A wav file in memory with the samples, interleaved.
+    fn wav<S: hound::Sample + Copy>(spec: hound::WavSpec, samples: &[S]) -> Vec<u8> {
+        let mut data = Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut data, spec).unwrap();
+        for &sample in samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        data.into_inner()
+    }
+
+    #[test]
+    fn a_stereo_wav_is_mixed_down_to_mono() {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 22050,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let data = wav::<i16>(spec, &[16384, -16384, 16384, 16384, -32768, -32768]);
+        let sound = Sound::from_wav(Cursor::new(data)).unwrap();
+        assert_eq!(sound.sample_rate, 22050);
+        assert_close(&sound.samples, &[0.0, 0.5, -1.0]);
+    }
+
+    #[test]
+    fn a_float_wav_keeps_its_samples() {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 44100,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let data = wav::<f32>(spec, &[0.25, -0.75]);
+        let sound = Sound::from_wav(Cursor::new(data)).unwrap();
+        assert_close(&sound.samples, &[0.25, -0.75]);
+    }
+
+    #[test]
+    fn a_file_that_isnt_a_wav_isnt_decoded() {
+        let result = Sound::from_wav(Cursor::new(b"not a wav file".to_vec()));
+        assert!(matches!(result, Err(AudioError::Wav(_))));
+    }
+
+    #[test]
+    fn files_are_loaded_by_their_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let path = dir.path().join("beep.WAV");
+        std::fs::write(&path, wav::<i16>(spec, &[0, 16384])).unwrap();
+        let sound = Sound::load(&path).unwrap();
+        assert_close(&sound.samples, &[0.0, 0.5]);
+
+        assert!(matches!(
+            Sound::load(dir.path().join("beep.mp3")),
+            Err(AudioError::UnsupportedFile(_))
+        ));
+        assert!(matches!(
+            Sound::load(dir.path().join("missing.ogg")),
+            Err(AudioError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn resampling_changes_the_length_by_the_ratio_of_rates() {
+        let sound = Sound {
+            samples: vec![0.0; 480],
+            sample_rate: 48000,
+        };
+        assert_eq!(sound.resampled(24000).samples.len(), 240);
+        assert_eq!(sound.resampled(96000).samples.len(), 960);
+        assert_eq!(sound.resampled(48000), sound);
+    }
+
+    #[test]
+    fn resampling_up_interpolates_between_samples() {
+        let sound = Sound {
+            samples: vec![0.0, 1.0],
+            sample_rate: 1,
+        };
+        let resampled = sound.resampled(2);
+        assert_eq!(resampled.sample_rate, 2);
+        assert_close(&resampled.samples, &[0.0, 0.5, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn a_tone_fades_in_and_out() {
+        let sound = Sound::tone(440.0, Duration::from_millis(100), 44100);
+        assert_eq!(sound.samples.len(), 4410);
+        assert_eq!(sound.samples[0], 0.0);
+        assert_eq!(*sound.samples.last().unwrap(), 0.0);
+        assert!(sound.samples.iter().any(|sample| sample.abs() > 0.9));
+    }
+}
@@ -0,0 +1,450 @@
+use std::f32::consts::TAU;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::sound::Sound;
+
+/// The classic Simon tones in hertz, in the order of the title screen's pads: green, red,
+/// yellow, and blue.
+pub const SIMON_PAD_FREQUENCIES: [f32; 4] = [415.0, 310.0, 252.0, 209.0];
+
+/// The shape of one cycle of an oscillator's wave.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Waveform {
+    #[default]
+    Sine,
+    Square,
+    Triangle,
+}
+
+impl Waveform {
+    /// The wave's value from -1 to 1 at a point in its cycle, from 0 to 1.
+    pub fn sample(&self, phase: f32) -> f32 {
+        match self {
+            Waveform::Sine => (TAU * phase).sin(),
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Triangle => 4.0 * (phase - 0.5).abs() - 1.0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Oscillator {
+    pub waveform: Waveform,
+    /// In hertz.
+    pub frequency: f32,
+    /// How loud the oscillator is, from 0 to 1.
+    pub amplitude: f32,
+    /// Where in its cycle the oscillator is, from 0 to 1.
+    phase: f32,
+}
+
+impl Oscillator {
+    pub fn new(waveform: Waveform, frequency: f32, amplitude: f32) -> Self {
+        Oscillator {
+            waveform,
+            frequency,
+            amplitude,
+            phase: 0.0,
+        }
+    }
+
+    /// The next sample, moving the oscillator forward by one sample.
+    pub fn next_sample(&mut self, sample_rate: u32) -> f32 {
+        let sample = self.waveform.sample(self.phase) * self.amplitude;
+        self.phase = (self.phase + self.frequency / sample_rate as f32).fract();
+        sample
+    }
+
+    /// Add the oscillator's next samples to a buffer.
+    pub fn render(&mut self, buffer: &mut [f32], sample_rate: u32) {
+        for sample in buffer.iter_mut() {
+            *sample += self.next_sample(sample_rate);
+        }
+    }
+}
+
+/// How a note's volume rises when it starts, falls to where it's held, and fades out after.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Envelope {
+    /// How long the note takes to reach full volume.
+    pub attack: Duration,
+    /// How long the note takes to fall from full volume to the sustain level.
+    pub decay: Duration,
+    /// The level the note is held at, from 0 to 1.
+    pub sustain: f32,
+    /// How long the note takes to fade out after it's let go.
+    pub release: Duration,
+}
+
+impl Envelope {
+    /// The volume from 0 to 1 at a time into a note held for a duration.
+    pub fn level(&self, time: Duration, held: Duration) -> f32 {
+        if time < held {
+            return self.held_level(time);
+        }
+        let released = time - held;
+        if released >= self.release {
+            return 0.0;
+        }
+        let fade = 1.0 - released.as_secs_f32() / self.release.as_secs_f32();
+        self.held_level(held) * fade
+    }
+
+    /// How long a note held for a duration is heard for, including its release.
+    pub fn length(&self, held: Duration) -> Duration {
+        held + self.release
+    }
+
+    /// The volume at a time while the note is held.
+    fn held_level(&self, time: Duration) -> f32 {
+        if time < self.attack {
+            return time.as_secs_f32() / self.attack.as_secs_f32();
+        }
+        let decaying = time - self.attack;
+        if decaying < self.decay {
+            let t = decaying.as_secs_f32() / self.decay.as_secs_f32();
+            return 1.0 + (self.sustain - 1.0) * t;
+        }
+        self.sustain
+    }
+}
+
+/// Oscillators played together through an envelope, like an instrument.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Patch {
+    pub oscillators: Vec<Oscillator>,
+    pub envelope: Envelope,
+}
+
+impl Patch {
+    /// A pad's tone, a bright triangle wave with a bit of square for bite.
+    pub fn simon_pad(frequency: f32) -> Self {
+        Patch {
+            oscillators: vec![
+                Oscillator::new(Waveform::Triangle, frequency, 0.6),
+                Oscillator::new(Waveform::Square, frequency, 0.1),
+            ],
+            envelope: Envelope {
+                attack: Duration::from_millis(5),
+                decay: Duration::from_millis(60),
+                sustain: 0.8,
+                release: Duration::from_millis(80),
+            },
+        }
+    }
+
+    /// The low, harsh buzz when the player gets the sequence wrong. Two square waves slightly
+    /// out of tune rattle against each other.
+    pub fn failure_buzzer() -> Self {
+        Patch {
+            oscillators: vec![
+                Oscillator::new(Waveform::Square, 42.0, 0.3),
+                Oscillator::new(Waveform::Square, 44.0, 0.3),
+            ],
+            envelope: Envelope {
+                attack: Duration::from_millis(5),
+                decay: Duration::ZERO,
+                sustain: 1.0,
+                release: Duration::from_millis(150),
+            },
+        }
+    }
+
+    /// Play a note held for a duration into a sound, including its release.
+    pub fn render(&self, held: Duration, sample_rate: u32) -> Sound {
+        let sample_count = (self.envelope.length(held).as_secs_f64() * sample_rate as f64) as usize;
+        let mut samples = vec![0.0; sample_count];
+        for oscillator in self.oscillators.iter() {
+            let mut oscillator = *oscillator;
+            oscillator.render(&mut samples, sample_rate);
+        }
+        for (i, sample) in samples.iter_mut().enumerate() {
+            let time = Duration::from_secs_f64(i as f64 / sample_rate as f64);
+            *sample = (*sample * self.envelope.level(time, held)).clamp(-1.0, 1.0);
+        }
+        Sound {
+            samples,
+            sample_rate,
+        }
+    }
+}
+
+/// A sound being mixed, and how far into it the mixer is.
+struct MixerVoice {
+    samples: Arc<[f32]>,
+    position: usize,
+    volume: f32,
+}
+
+/// Mixes sounds playing at the same time into one buffer, for outputs to play. Sounds are
+/// dropped once they've been mixed to the end.
+pub struct Mixer {
+    voices: Vec<MixerVoice>,
+    /// Everything is scaled by this, from 0 to 1.
+    pub volume: f32,
+}
+
+impl Mixer {
+    pub fn new() -> Self {
+        Mixer {
+            voices: Vec::new(),
+            volume: 1.0,
+        }
+    }
+
+    /// Start mixing a sound in from the next sample, at a volume from 0 to 1.
+    pub fn add(&mut self, samples: Arc<[f32]>, volume: f32) {
+        if !samples.is_empty() {
+            self.voices.push(MixerVoice {
+                samples,
+                position: 0,
+                volume,
+            });
+        }
+    }
+
+    /// Stop every sound.
+    pub fn clear(&mut self) {
+        self.voices.clear();
+    }
+
+    /// How many sounds are still playing.
+    pub fn voice_count(&self) -> usize {
+        self.voices.len()
+    }
+
+    /// Fill a buffer with the next samples of every sound added together, clipped to -1 to 1.
+    pub fn mix(&mut self, buffer: &mut [f32]) {
+        buffer.fill(0.0);
+        for voice in self.voices.iter_mut() {
+            let remaining = &voice.samples[voice.position..];
+            for (mixed, sample) in buffer.iter_mut().zip(remaining) {
+                *mixed += sample * voice.volume;
+            }
+            voice.position += remaining.len().min(buffer.len());
+        }
+        self.voices
+            .retain(|voice| voice.position < voice.samples.len());
+        for mixed in buffer.iter_mut() {
+            *mixed = (*mixed * self.volume).clamp(-1.0, 1.0);
+        }
+    }
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 0.001,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn waveforms_start_their_cycle_where_they_should() {
+        assert_close(Waveform::Sine.sample(0.0), 0.0);
+        assert_close(Waveform::Sine.sample(0.25), 1.0);
+        assert_close(Waveform::Sine.sample(0.75), -1.0);
+        assert_close(Waveform::Square.sample(0.25), 1.0);
+        assert_close(Waveform::Square.sample(0.75), -1.0);
+        assert_close(Waveform::Triangle.sample(0.0), 1.0);
+        assert_close(Waveform::Triangle.sample(0.25), 0.0);
+        assert_close(Waveform::Triangle.sample(0.5), -1.0);
+    }
+
+    #[test]
+    fn an_oscillator_wraps_its_phase_each_cycle() {
+        // A quarter of a cycle a sample.
+        let mut oscillator = Oscillator::new(Waveform::Square, 1.0, 0.5);
+        let samples: Vec<f32> = (0..6).map(|_| oscillator.next_sample(4)).collect();
+        assert_eq!(samples, [0.5, 0.5, -0.5, -0.5, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn an_oscillator_adds_to_a_buffer() {
+        let mut oscillator = Oscillator::new(Waveform::Sine, 1.0, 0.5);
+        let mut buffer = [1.0; 4];
+        oscillator.render(&mut buffer, 4);
+        for (sample, expected) in buffer.iter().zip([1.0, 1.5, 1.0, 0.5]) {
+            assert_close(*sample, expected);
+        }
+    }
+
+    /// The loudest sample.
+    fn peak(samples: &[f32]) -> f32 {
+        samples
+            .iter()
+            .fold(0.0, |peak: f32, sample| peak.max(sample.abs()))
+    }
+
+    fn patch() -> Patch {
+        Patch {
+            oscillators: vec![
+                Oscillator::new(Waveform::Square, 100.0, 0.8),
+                Oscillator::new(Waveform::Square, 100.0, 0.8),
+            ],
+            envelope: Envelope {
+                attack: Duration::ZERO,
+                decay: Duration::ZERO,
+                sustain: 1.0,
+                release: ms(100),
+            },
+        }
+    }
+
+    #[test]
+    fn a_patch_renders_the_note_and_its_release() {
+        let sound = patch().render(ms(200), 1000);
+        assert_eq!(sound.sample_rate, 1000);
+        assert_eq!(sound.samples.len(), 300);
+    }
+
+    #[test]
+    fn a_patch_is_clipped_to_one() {
+        // Two oscillators at 0.8 add up to 1.6 while held.
+        let sound = patch().render(ms(200), 1000);
+        assert!(sound.samples.iter().all(|sample| sample.abs() <= 1.0));
+        assert_eq!(sound.samples[1], 1.0);
+        assert_eq!(sound.samples[6], -1.0);
+    }
+
+    #[test]
+    fn a_patch_fades_out_to_silence() {
+        let sound = patch().render(ms(200), 1000);
+        // Half way through the release the 1.6 held is down to 0.8.
+        assert_close(peak(&sound.samples[250..260]), 0.8);
+        assert!(peak(&sound.samples[295..]) < 0.1);
+    }
+
+    #[test]
+    fn the_games_patches_make_sound() {
+        let held = ms(300);
+        let patches = [
+            Patch::simon_pad(SIMON_PAD_FREQUENCIES[0]),
+            Patch::failure_buzzer(),
+        ];
+        for patch in patches {
+            let sound = patch.render(held, 44100);
+            let length = patch.envelope.length(held).as_secs_f64() * 44100.0;
+            assert_eq!(sound.samples.len(), length as usize);
+            let peak = peak(&sound.samples);
+            assert!(peak > 0.3, "{:?} peaks at {}", patch, peak);
+        }
+    }
+
+    fn envelope() -> Envelope {
+        Envelope {
+            attack: ms(10),
+            decay: ms(20),
+            sustain: 0.5,
+            release: ms(40),
+        }
+    }
+
+    #[test]
+    fn the_envelope_rises_through_the_attack() {
+        let envelope = envelope();
+        let held = ms(100);
+        assert_close(envelope.level(ms(0), held), 0.0);
+        assert_close(envelope.level(ms(5), held), 0.5);
+        assert_close(envelope.level(ms(10), held), 1.0);
+    }
+
+    #[test]
+    fn the_envelope_decays_to_the_sustain_level_and_holds_it() {
+        let envelope = envelope();
+        let held = ms(100);
+        assert_close(envelope.level(ms(20), held), 0.75);
+        assert_close(envelope.level(ms(30), held), 0.5);
+        assert_close(envelope.level(ms(99), held), 0.5);
+    }
+
+    #[test]
+    fn the_envelope_fades_out_over_the_release() {
+        let envelope = envelope();
+        let held = ms(100);
+        assert_close(envelope.level(ms(100), held), 0.5);
+        assert_close(envelope.level(ms(120), held), 0.25);
+        assert_close(envelope.level(ms(140), held), 0.0);
+        assert_close(envelope.level(ms(500), held), 0.0);
+        assert_eq!(envelope.length(held), ms(140));
+    }
+
+    #[test]
+    fn a_note_let_go_during_its_attack_fades_from_where_it_got_to() {
+        let envelope = envelope();
+        let held = ms(5);
+        assert_close(envelope.level(ms(5), held), 0.5);
+        assert_close(envelope.level(ms(25), held), 0.25);
+    }
+
+    #[test]
+    fn the_mixer_adds_sounds_at_their_volumes() {
+        let mut mixer = Mixer::new();
+        mixer.add(Arc::from([0.2, 0.2, 0.2]), 1.0);
+        mixer.add(Arc::from([0.4]), 0.5);
+        let mut buffer = [1.0; 4];
+        mixer.mix(&mut buffer);
+        assert_close(buffer[0], 0.4);
+        assert_close(buffer[1], 0.2);
+        assert_close(buffer[2], 0.2);
+        assert_close(buffer[3], 0.0);
+        assert_eq!(mixer.voice_count(), 0);
+    }
+
+    #[test]
+    fn the_mixer_clips_to_one() {
+        let mut mixer = Mixer::new();
+        mixer.add(Arc::from([0.8, -0.8]), 1.0);
+        mixer.add(Arc::from([0.8, -0.8]), 1.0);
+        let mut buffer = [0.0; 2];
+        mixer.mix(&mut buffer);
+        assert_eq!(buffer, [1.0, -1.0]);
+    }
+
+    #[test]
+    fn the_mixer_volume_scales_before_clipping() {
+        let mut mixer = Mixer::new();
+        mixer.volume = 0.5;
+        mixer.add(Arc::from([0.8, 1.0]), 1.0);
+        mixer.add(Arc::from([0.8, 1.0]), 1.0);
+        let mut buffer = [0.0; 2];
+        mixer.mix(&mut buffer);
+        assert_close(buffer[0], 0.8);
+        assert_close(buffer[1], 1.0);
+    }
+
+    #[test]
+    fn the_mixer_carries_on_from_where_it_left_off() {
+        let mut mixer = Mixer::new();
+        mixer.add(Arc::from([0.1, 0.2, 0.3]), 1.0);
+        let mut buffer = [0.0; 2];
+        mixer.mix(&mut buffer);
+        assert_eq!(mixer.voice_count(), 1);
+        mixer.mix(&mut buffer);
+        assert_close(buffer[0], 0.3);
+        assert_close(buffer[1], 0.0);
+        assert_eq!(mixer.voice_count(), 0);
+    }
+}
@@ -4,11 +4,12 @@ use glium::glutin::event::{VirtualKeyCode, WindowEvent};
 
 use crate::ecs::{component::text::TextComponent, World};
 
+#[derive(Default)]
 pub struct InputSystem {
     context: Option<Box<dyn Context>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct ProcessedInput {
     keydown: HashMap<VirtualKeyCode, VirtualKeyCode>,
 }
@@ -51,15 +52,12 @@ impl InputSystem {
 
     pub fn process_input(&self, event: &WindowEvent, world: &mut World) {
         let mut processed_input = ProcessedInput::new();
-        match event {
-            WindowEvent::KeyboardInput { input, .. } => {
-                if let Some(virtual_keycode) = input.virtual_keycode {
-                    processed_input
-                        .keydown
-                        .insert(virtual_keycode, virtual_keycode);
-                }
+        if let WindowEvent::KeyboardInput { input, .. } = event {
+            if let Some(virtual_keycode) = input.virtual_keycode {
+                processed_input
+                    .keydown
+                    .insert(virtual_keycode, virtual_keycode);
             }
-            _ => {}
         }
         if let Some(context) = self.context.as_ref() {
             context.dispatch_input(&mut processed_input, world);
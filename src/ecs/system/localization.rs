@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::ecs::component::text::{TextComponent, TextMessage};
+use crate::ecs::World;
+
+#[derive(Debug)]
+pub enum LocalizationError {
+    /// The catalog file couldn't be read from disk.
+    Io(std::io::Error),
+    /// A line in a catalog wasn't a comment, blank, or a `key = value` message.
+    InvalidLine { line: usize },
+}
+
+impl fmt::Display for LocalizationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LocalizationError::Io(err) => write!(f, "could not read message catalog: {}", err),
+            LocalizationError::InvalidLine { line } => {
+                write!(f, "line {} of message catalog is not `key = value`", line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LocalizationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LocalizationError::Io(err) => Some(err),
+            LocalizationError::InvalidLine { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for LocalizationError {
+    fn from(err: std::io::Error) -> Self {
+        LocalizationError::Io(err)
+    }
+}
+
+/// The messages of one locale, by key.
+#[derive(Debug, Default)]
+pub struct Catalog {
+    messages: HashMap<String, String>,
+}
+
+impl Catalog {
+    /// Parse a catalog with one `key = value` message per line. Blank lines and lines starting
+    /// with `#` are skipped. Values can reference arguments like `Score: {score}`.
+    pub fn parse(source: &str) -> Result<Self, LocalizationError> {
+        let mut messages = HashMap::new();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or(LocalizationError::InvalidLine { line: index + 1 })?;
+            let key = key.trim();
+            if key.is_empty() {
+                return Err(LocalizationError::InvalidLine { line: index + 1 });
+            }
+            messages.insert(key.to_string(), value.trim().to_string());
+        }
+        Ok(Catalog { messages })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.messages.get(key).map(String::as_str)
+    }
+}
+
+/// Turns the message keys of text components into text in the current locale. Messages
+/// missing from the current locale come from the fallback locale, and messages missing from
+/// both are shown as their key so they're easy to spot.
+pub struct LocalizationSystem {
+    catalogs: HashMap<String, Catalog>,
+    locale: String,
+    fallback_locale: String,
+}
+
+impl LocalizationSystem {
+    /// Make a system that starts in and falls back to the given locale, like "en".
+    pub fn new(fallback_locale: &str) -> Self {
+        LocalizationSystem {
+            catalogs: HashMap::new(),
+            locale: fallback_locale.to_string(),
+            fallback_locale: fallback_locale.to_string(),
+        }
+    }
+
+    /// Load a catalog's source for a locale, replacing any catalog it had before.
+    pub fn load_catalog(&mut self, locale: &str, source: &str) -> Result<(), LocalizationError> {
+        self.catalogs
+            .insert(locale.to_string(), Catalog::parse(source)?);
+        Ok(())
+    }
+
+    /// Load the catalogs for every locale the game ships with.
+    pub fn load_builtin_catalogs(&mut self) -> Result<(), LocalizationError> {
+        self.load_catalog("en", include_str!("../../locales/en.txt"))?;
+        self.load_catalog("fr", include_str!("../../locales/fr.txt"))
+    }
+
+    pub fn load_catalog_file<P: AsRef<Path>>(
+        &mut self,
+        locale: &str,
+        path: P,
+    ) -> Result<(), LocalizationError> {
+        let source = std::fs::read_to_string(path)?;
+        self.load_catalog(locale, &source)
+    }
+
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// Switch locales. Text components with a message are re-resolved the next time the world
+    /// is updated.
+    pub fn set_locale(&mut self, locale: &str) {
+        self.locale = locale.to_string();
+    }
+
+    /// The locales that have a catalog loaded.
+    pub fn locales(&self) -> impl Iterator<Item = &str> {
+        self.catalogs.keys().map(String::as_str)
+    }
+
+    /// Look up a message in the current locale and fill in its arguments.
+    pub fn format(&self, message: &TextMessage) -> String {
+        let template = [&self.locale, &self.fallback_locale]
+            .into_iter()
+            .find_map(|locale| self.catalogs.get(locale)?.get(&message.key))
+            .unwrap_or(&message.key);
+        interpolate(template, &message.args)
+    }
+
+    /// Set the text of every text component that has a message to the message in the
+    /// current locale.
+    pub fn update_world(&self, world: &World) {
+        let mut texts = match world.borrow_component_vec::<TextComponent>() {
+            Some(texts) => texts,
+            None => return,
+        };
+        for text in texts.iter_mut().filter_map(|text| text.as_mut()) {
+            if let Some(message) = &text.message {
+                let resolved = self.format(message);
+                // Only assign when it changed so the text's layout stays cached.
+                if text.text != resolved {
+                    text.text = resolved;
+                }
+            }
+        }
+    }
+}
+
+/// Replace each `{name}` in a template with the argument of that name. `{{` and `}}` are a
+/// literal brace, and references to missing arguments are left as they are.
+fn interpolate(template: &str, args: &[(String, String)]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            result.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        let end = match rest.find('}') {
+            Some(end) if rest.starts_with('{') => end,
+            _ => {
+                result.push_str(&rest[..1]);
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let name = &rest[1..end];
+        match args.iter().find(|(arg_name, _)| arg_name == name) {
+            Some((_, value)) => result.push_str(value),
+            None => result.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[(&str, &str)]) -> Vec<(String, String)> {
+        args.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn system() -> LocalizationSystem {
+        let mut localization_system = LocalizationSystem::new("en");
+        localization_system
+            .load_catalog("en", "title = Simon Says\nscore = Score: {score}")
+            .unwrap();
+        localization_system
+            .load_catalog("fr", "title = Jacques a dit")
+            .unwrap();
+        localization_system
+    }
+
+    #[test]
+    fn a_catalog_skips_comments_and_blank_lines() {
+        let catalog = Catalog::parse(
+            "# The title screen\n\n  title  =  Simon Says  \n   \n# score = no\nequation = 1 + 1 = 2\nempty =",
+        )
+        .unwrap();
+        assert_eq!(catalog.get("title"), Some("Simon Says"));
+        assert_eq!(catalog.get("score"), None);
+        assert_eq!(catalog.get("equation"), Some("1 + 1 = 2"));
+        assert_eq!(catalog.get("empty"), Some(""));
+    }
+
+    #[test]
+    fn a_catalog_line_without_a_key_and_value_is_an_error() {
+        assert!(matches!(
+            Catalog::parse("title = Simon Says\n\njust some words"),
+            Err(LocalizationError::InvalidLine { line: 3 })
+        ));
+        assert!(matches!(
+            Catalog::parse("= no key"),
+            Err(LocalizationError::InvalidLine { line: 1 })
+        ));
+    }
+
+    #[test]
+    fn arguments_are_filled_in_by_name() {
+        assert_eq!(
+            interpolate(
+                "{name} scored {score}, {name}!",
+                &args(&[("score", "12"), ("name", "Ada")])
+            ),
+            "Ada scored 12, Ada!"
+        );
+    }
+
+    #[test]
+    fn doubled_braces_are_literal_braces() {
+        assert_eq!(
+            interpolate("{{score}} is {score} }}", &args(&[("score", "12")])),
+            "{score} is 12 }"
+        );
+    }
+
+    #[test]
+    fn unknown_arguments_and_stray_braces_are_left_as_they_are() {
+        assert_eq!(
+            interpolate("{missing} {score} { } {", &args(&[("score", "12")])),
+            "{missing} 12 { } {"
+        );
+    }
+
+    #[test]
+    fn messages_missing_from_the_locale_come_from_the_fallback() {
+        let mut localization_system = system();
+        localization_system.set_locale("fr");
+        let format = |key| localization_system.format(&TextMessage::new(key).arg("score", 3));
+        assert_eq!(format("title"), "Jacques a dit");
+        assert_eq!(format("score"), "Score: 3");
+        // Missing from both, so the key is shown.
+        assert_eq!(format("missing"), "missing");
+    }
+
+    #[test]
+    fn changing_the_locale_changes_text_on_the_next_update() {
+        let mut localization_system = system();
+        let mut world = World::new();
+        let entity = world.new_entity();
+        world.add_component_to_entity(
+            entity,
+            TextComponent {
+                message: Some(TextMessage::new("title")),
+                ..Default::default()
+            },
+        );
+        let text = |world: &mut World| {
+            world
+                .borrow_component::<TextComponent>(entity)
+                .unwrap()
+                .text
+                .clone()
+        };
+
+        localization_system.update_world(&world);
+        assert_eq!(text(&mut world), "Simon Says");
+        localization_system.set_locale("fr");
+        assert_eq!(text(&mut world), "Simon Says");
+        localization_system.update_world(&world);
+        assert_eq!(text(&mut world), "Jacques a dit");
+    }
+
+    #[test]
+    fn the_builtin_catalogs_load() {
+        let mut localization_system = LocalizationSystem::new("en");
+        localization_system.load_builtin_catalogs().unwrap();
+        let mut locales: Vec<&str> = localization_system.locales().collect();
+        locales.sort();
+        assert_eq!(locales, ["en", "fr"]);
+    }
+}
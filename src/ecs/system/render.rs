@@ -0,0 +1,496 @@
+pub mod glium_renderer;
+pub mod glyph;
+#[cfg(target_os = "linux")]
+pub mod headless;
+pub mod image;
+pub mod outline;
+pub mod quad;
+pub mod recording;
+pub mod sdf;
+pub mod shader;
+
+use glium::backend::Facade;
+use glium::program::ProgramCreationError;
+use glium::texture::TextureCreationError;
+use glium::vertex::{BufferCreationError, VertexBufferSlice};
+use glium::{DrawError, SwapBuffersError, VertexBuffer};
+use rusttype::{PositionedGlyph, Rect};
+use std::fmt;
+
+use super::sprite::SpriteSystem;
+use super::text::font::{FontError, FontHandle};
+use super::text::TextSystem;
+use crate::ecs::component::render_layer::RenderLayer;
+use crate::ecs::component::sprite::SpriteComponent;
+use crate::ecs::component::text::{TextComponent, TextEffects};
+use crate::ecs::component::transform::{TransformComponent, Viewport};
+use crate::ecs::World;
+
+#[derive(Debug)]
+pub enum RenderError {
+    /// A shader didn't compile or link.
+    Program(ProgramCreationError),
+    /// The context doesn't support any glsl version shaders are written for, only this one.
+    UnsupportedGlsl(glium::Version),
+    Texture(TextureCreationError),
+    VertexBuffer(BufferCreationError),
+    Draw(DrawError),
+    SwapBuffers(SwapBuffersError),
+    /// The opengl context was lost, like when the gpu was reset or its driver was updated,
+    /// and everything on the gpu with it. The renderer has to be recreated with a new context.
+    ContextLost,
+    /// Text couldn't be laid out, like when no fonts are loaded.
+    Font(FontError),
+    /// A frame's glyphs didn't fit in a glyph cache even at the biggest texture the gpu
+    /// supports. The frame was still drawn, without the glyphs that didn't fit.
+    GlyphCacheFull { width: u32, height: u32 },
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::Program(err) => write!(f, "could not create shader program: {}", err),
+            RenderError::UnsupportedGlsl(glium::Version(api, major, minor)) => write!(
+                f,
+                "no shaders for the context's glsl version, {:?} {}.{}",
+                api, major, minor
+            ),
+            RenderError::Texture(err) => write!(f, "could not create texture: {}", err),
+            RenderError::VertexBuffer(err) => write!(f, "could not create vertex buffer: {}", err),
+            RenderError::Draw(err) => write!(f, "could not draw: {}", err),
+            RenderError::SwapBuffers(err) => write!(f, "could not swap buffers: {}", err),
+            RenderError::ContextLost => write!(f, "the opengl context was lost"),
+            RenderError::Font(err) => write!(f, "could not lay out text: {}", err),
+            RenderError::GlyphCacheFull { width, height } => write!(
+                f,
+                "some glyphs weren't drawn, the glyph cache can't grow past {}x{}",
+                width, height
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RenderError::Program(err) => Some(err),
+            RenderError::UnsupportedGlsl(_) => None,
+            RenderError::Texture(err) => Some(err),
+            RenderError::VertexBuffer(err) => Some(err),
+            RenderError::Draw(err) => Some(err),
+            RenderError::SwapBuffers(err) => Some(err),
+            RenderError::ContextLost => None,
+            RenderError::Font(err) => Some(err),
+            RenderError::GlyphCacheFull { .. } => None,
+        }
+    }
+}
+
+impl From<ProgramCreationError> for RenderError {
+    fn from(err: ProgramCreationError) -> Self {
+        RenderError::Program(err)
+    }
+}
+
+impl From<TextureCreationError> for RenderError {
+    fn from(err: TextureCreationError) -> Self {
+        RenderError::Texture(err)
+    }
+}
+
+impl From<BufferCreationError> for RenderError {
+    fn from(err: BufferCreationError) -> Self {
+        RenderError::VertexBuffer(err)
+    }
+}
+
+impl From<DrawError> for RenderError {
+    fn from(err: DrawError) -> Self {
+        RenderError::Draw(err)
+    }
+}
+
+impl From<FontError> for RenderError {
+    fn from(err: FontError) -> Self {
+        RenderError::Font(err)
+    }
+}
+
+impl From<SwapBuffersError> for RenderError {
+    fn from(err: SwapBuffersError) -> Self {
+        match err {
+            SwapBuffersError::ContextLost => RenderError::ContextLost,
+            err => RenderError::SwapBuffers(err),
+        }
+    }
+}
+
+/// Draws what systems ask it to. Systems only describe what to draw, like quads and runs of
+/// text in physical pixels, so they can run with a recording renderer where there's no window
+/// or gpu, like in tests and on CI.
+pub trait Renderer {
+    /// The size and scale factor of what's being drawn to.
+    fn viewport(&self) -> Viewport;
+
+    /// Start a frame, clearing it to a colour.
+    fn begin_frame(&mut self, clear_colour: [f32; 4]) -> Result<(), RenderError>;
+
+    /// Draw quads in order, so later quads are over earlier ones.
+    fn draw_quads(&mut self, quads: &[Quad]) -> Result<(), RenderError>;
+
+    fn draw_text(&mut self, runs: &[TextRun]) -> Result<(), RenderError>;
+
+    /// Draw one pixel outlines of rects, for debugging.
+    fn draw_outlines(&mut self, outlines: &[(Rect<f32>, [f32; 4])]) -> Result<(), RenderError>;
+
+    /// Finish the frame and show it. Gives RenderError::GlyphCacheFull after showing it if
+    /// some of its glyphs couldn't be drawn.
+    fn end_frame(&mut self) -> Result<(), RenderError>;
+
+    /// Load an image from its pixels, 4 bytes of rgba per pixel, starting at the top left.
+    fn load_texture_rgba(
+        &mut self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<TextureHandle, RenderError>;
+}
+
+/// Refers to a texture loaded into a Renderer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TextureHandle(usize);
+
+impl TextureHandle {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// A rectangle with rounded corners, filled with a colour and optionally a texture.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Quad {
+    /// In physical pixels from the top left of the window.
+    pub rect: Rect<f32>,
+    /// How round the corners are in physical pixels. A square with a radius of half its size
+    /// is a circle.
+    pub radius: f32,
+    /// Multiplied with the texture's colour.
+    pub colour: [f32; 4],
+    pub texture: Option<TextureHandle>,
+}
+
+/// The glyphs of one text, all drawn in the same style.
+#[derive(Clone, Debug)]
+pub struct TextRun {
+    /// In physical pixels from the top left of the window.
+    pub glyphs: Vec<PositionedGlyph<'static>>,
+    /// The font each glyph came from.
+    pub fonts: Vec<FontHandle>,
+    pub colour: [f32; 4],
+    pub effects: TextEffects,
+}
+
+/// The kinds of components that can be drawn.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Drawable {
+    Sprite,
+    Text,
+}
+
+/// One component to draw, from an entity.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RenderItem {
+    pub layer: RenderLayer,
+    pub entity: usize,
+    pub drawable: Drawable,
+}
+
+/// Sorts everything drawn in a frame by layer before it's drawn. Items in the same layer keep
+/// the order they were pushed in.
+#[derive(Default)]
+pub struct RenderQueue {
+    items: Vec<RenderItem>,
+}
+
+impl RenderQueue {
+    pub fn new() -> Self {
+        RenderQueue { items: Vec::new() }
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    pub fn push(&mut self, item: RenderItem) {
+        self.items.push(item);
+    }
+
+    /// Push every drawable component in the world in entity order. An entity's sprite is
+    /// pushed before its text, so a button's label is drawn over its background when they're
+    /// in the same layer.
+    pub fn push_world(&mut self, world: &World) {
+        let sprites = world.borrow_component_vec::<SpriteComponent>();
+        let texts = world.borrow_component_vec::<TextComponent>();
+        let entity_count = sprites
+            .as_ref()
+            .map_or(0, |sprites| sprites.len())
+            .max(texts.as_ref().map_or(0, |texts| texts.len()));
+        for entity in 0..entity_count {
+            if let Some(Some(sprite)) = sprites.as_ref().map(|sprites| &sprites[entity]) {
+                self.push(RenderItem {
+                    layer: sprite.layer,
+                    entity,
+                    drawable: Drawable::Sprite,
+                });
+            }
+            if let Some(Some(text)) = texts.as_ref().map(|texts| &texts[entity]) {
+                self.push(RenderItem {
+                    layer: text.layer,
+                    entity,
+                    drawable: Drawable::Text,
+                });
+            }
+        }
+    }
+
+    /// Sort items from the lowest layer to the highest.
+    pub fn sort(&mut self) {
+        // sort_by_key is stable, so items in a layer stay in the order they were pushed.
+        self.items.sort_by_key(|item| item.layer);
+    }
+
+    pub fn items(&self) -> &[RenderItem] {
+        &self.items
+    }
+
+    /// Runs of items in a row that are drawn by the same system, so each run can be drawn in
+    /// one batch.
+    pub fn batches(&self) -> impl Iterator<Item = (Drawable, &[RenderItem])> {
+        self.items
+            .chunk_by(|a, b| a.drawable == b.drawable)
+            .map(|batch| (batch[0].drawable, batch))
+    }
+}
+
+/// Draws every drawable component in the world in layer order, handing each batch to the
+/// system that draws it.
+#[derive(Default)]
+pub struct RenderSystem {
+    queue: RenderQueue,
+}
+
+impl RenderSystem {
+    pub fn new() -> Self {
+        RenderSystem {
+            queue: RenderQueue::new(),
+        }
+    }
+
+    pub fn draw_world(
+        &mut self,
+        renderer: &mut dyn Renderer,
+        world: &World,
+        sprite_system: &mut SpriteSystem,
+        text_system: &mut TextSystem,
+    ) -> Result<(), RenderError> {
+        self.queue.clear();
+        self.queue.push_world(world);
+        self.queue.sort();
+
+        let sprites = world.borrow_component_vec::<SpriteComponent>();
+        let texts = world.borrow_component_vec::<TextComponent>();
+        let transforms = match world.borrow_component_vec::<TransformComponent>() {
+            Some(transforms) => transforms,
+            None => return Ok(()),
+        };
+        for (drawable, batch) in self.queue.batches() {
+            let entities = batch.iter().map(|item| item.entity);
+            match drawable {
+                Drawable::Sprite => {
+                    let sprites = match &sprites {
+                        Some(sprites) => sprites,
+                        None => continue,
+                    };
+                    sprite_system.draw_batch(
+                        renderer,
+                        entities.filter_map(|entity| {
+                            Some((sprites[entity].as_ref()?, transforms[entity].as_ref()?))
+                        }),
+                    )?;
+                }
+                Drawable::Text => {
+                    let texts = match &texts {
+                        Some(texts) => texts,
+                        None => continue,
+                    };
+                    text_system.draw_batch(
+                        renderer,
+                        entities.filter_map(|entity| {
+                            Some((entity, texts[entity].as_ref()?, transforms[entity].as_ref()?))
+                        }),
+                    )?;
+                }
+            }
+        }
+        text_system.end_frame();
+        Ok(())
+    }
+}
+
+/// Write vertices into a vertex buffer kept from earlier frames, only making a bigger one when
+/// this frame's vertices don't fit.
+pub fn reuse_vertex_buffer<'b, V: glium::Vertex>(
+    buffer: &'b mut Option<VertexBuffer<V>>,
+    facade: &impl Facade,
+    vertices: &[V],
+) -> Result<VertexBufferSlice<'b, V>, RenderError> {
+    let buffer_too_small = buffer
+        .as_ref()
+        .is_none_or(|buffer| buffer.len() < vertices.len());
+    if buffer_too_small {
+        *buffer = Some(VertexBuffer::empty_dynamic(
+            facade,
+            vertices.len().next_power_of_two(),
+        )?);
+    }
+    let slice = buffer.as_ref().unwrap().slice(0..vertices.len()).unwrap();
+    slice.write(vertices);
+    Ok(slice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::recording::{DrawCommand, RecordingRenderer};
+    use super::*;
+
+    fn item(layer: RenderLayer, entity: usize, drawable: Drawable) -> RenderItem {
+        RenderItem {
+            layer,
+            entity,
+            drawable,
+        }
+    }
+
+    fn add_sprite(world: &mut World, layer: RenderLayer, colour: [f32; 4]) {
+        let entity = world.new_entity();
+        world.add_component_to_entity(
+            entity,
+            SpriteComponent {
+                colour,
+                layer,
+                ..Default::default()
+            },
+        );
+    }
+
+    fn add_text(world: &mut World, layer: RenderLayer, text: &str) {
+        let entity = world.new_entity();
+        world.add_component_to_entity(
+            entity,
+            TextComponent {
+                text: text.to_string(),
+                layer,
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn queue_sorts_by_layer_and_keeps_push_order_within_a_layer() {
+        let mut queue = RenderQueue::new();
+        queue.push(item(RenderLayer::UI, 0, Drawable::Text));
+        queue.push(item(RenderLayer::GAMEPLAY, 1, Drawable::Sprite));
+        queue.push(item(RenderLayer::UI, 2, Drawable::Sprite));
+        queue.push(item(RenderLayer::BACKGROUND, 3, Drawable::Sprite));
+        queue.push(item(RenderLayer::GAMEPLAY, 4, Drawable::Text));
+        queue.sort();
+        let entities: Vec<usize> = queue.items().iter().map(|item| item.entity).collect();
+        assert_eq!(entities, [3, 1, 4, 0, 2]);
+    }
+
+    #[test]
+    fn batches_are_runs_of_the_same_drawable() {
+        let mut queue = RenderQueue::new();
+        queue.push(item(RenderLayer::GAMEPLAY, 0, Drawable::Sprite));
+        queue.push(item(RenderLayer::GAMEPLAY, 1, Drawable::Sprite));
+        queue.push(item(RenderLayer::UI, 2, Drawable::Text));
+        queue.push(item(RenderLayer::UI, 3, Drawable::Text));
+        queue.push(item(RenderLayer::OVERLAY, 4, Drawable::Sprite));
+        let batches: Vec<(Drawable, Vec<usize>)> = queue
+            .batches()
+            .map(|(drawable, batch)| (drawable, batch.iter().map(|item| item.entity).collect()))
+            .collect();
+        assert_eq!(
+            batches,
+            [
+                (Drawable::Sprite, vec![0, 1]),
+                (Drawable::Text, vec![2, 3]),
+                (Drawable::Sprite, vec![4]),
+            ]
+        );
+    }
+
+    #[test]
+    fn push_world_puts_an_entitys_sprite_before_its_text() {
+        let mut world = World::new();
+        add_sprite(&mut world, RenderLayer::UI, [1.0; 4]);
+        world.add_component_to_entity(0, TextComponent::default());
+        let mut queue = RenderQueue::new();
+        queue.push_world(&world);
+        let drawables: Vec<Drawable> = queue.items().iter().map(|item| item.drawable).collect();
+        assert_eq!(drawables, [Drawable::Sprite, Drawable::Text]);
+    }
+
+    #[test]
+    fn draw_world_draws_each_batch_in_layer_order() {
+        const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+        const GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+        const BLUE: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+        const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+        let mut world = World::new();
+        add_sprite(&mut world, RenderLayer::OVERLAY, WHITE);
+        add_text(&mut world, RenderLayer::UI, "Simon");
+        add_sprite(&mut world, RenderLayer::GAMEPLAY, RED);
+        add_text(&mut world, RenderLayer::UI, "Says");
+        add_sprite(&mut world, RenderLayer::BACKGROUND, GREEN);
+        add_sprite(&mut world, RenderLayer::GAMEPLAY, BLUE);
+
+        let mut renderer = RecordingRenderer::new(Viewport {
+            width: 512.0,
+            height: 512.0,
+            scale_factor: 1.0,
+        });
+        let mut text_system = TextSystem::new().unwrap();
+        RenderSystem::new()
+            .draw_world(
+                &mut renderer,
+                &world,
+                &mut SpriteSystem::new(),
+                &mut text_system,
+            )
+            .unwrap();
+
+        let commands = renderer.commands();
+        assert_eq!(commands.len(), 3, "{:?}", commands);
+        // The background and gameplay sprites are one batch, drawn under the text.
+        match &commands[0] {
+            DrawCommand::Quads(quads) => {
+                let colours: Vec<[f32; 4]> = quads.iter().map(|quad| quad.colour).collect();
+                assert_eq!(colours, [GREEN, RED, BLUE]);
+            }
+            command => panic!("expected quads, got {:?}", command),
+        }
+        match &commands[1] {
+            DrawCommand::Text(runs) => {
+                let glyph_counts: Vec<usize> = runs.iter().map(|run| run.glyphs.len()).collect();
+                assert_eq!(glyph_counts, ["Simon".len(), "Says".len()]);
+            }
+            command => panic!("expected text, got {:?}", command),
+        }
+        match &commands[2] {
+            DrawCommand::Quads(quads) => assert_eq!(quads[0].colour, WHITE),
+            command => panic!("expected quads, got {:?}", command),
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use glium::backend::{Context, Facade};
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::texture::{MipmapsOption, RawImage2d, Texture2dMultisample, UncompressedFloatFormat};
+use glium::uniforms::MagnifySamplerFilter;
+use glium::{Display, Surface, Texture2d};
+use rusttype::Rect;
+use std::rc::Rc;
+
+use super::glyph::GlyphRenderer;
+use super::image::RgbaImage;
+use super::outline::OutlineRenderer;
+use super::quad::QuadRenderer;
+use super::shader::ShaderVersion;
+use super::{Quad, RenderError, Renderer, TextRun, TextureHandle};
+use crate::ecs::component::transform::Viewport;
+
+/// Draws with opengl through glium. Frames are drawn into a texture, then copied to the window
+/// if there is one, so the last frame can always be read back with read_frame.
+pub struct GliumRenderer {
+    context: Rc<Context>,
+    /// The window frames are shown in, or None when only drawing offscreen.
+    display: Option<Display>,
+    /// The viewport drawn to when there's no window.
+    offscreen_viewport: Viewport,
+    /// What frames are drawn into, the size of the viewport in physical pixels.
+    target: Texture2d,
+    /// With msaa, frames are drawn into this instead, then resolved into target.
+    multisample_target: Option<Texture2dMultisample>,
+    /// Samples per pixel for msaa, 0 when it's off.
+    msaa: u32,
+    /// The glsl version shaders were compiled as, picked for the context.
+    shader_version: ShaderVersion,
+    quad_renderer: QuadRenderer,
+    glyph_renderer: GlyphRenderer,
+    outline_renderer: OutlineRenderer,
+    /// Set when glyphs didn't fit in a glyph cache, and returned from end_frame once the rest
+    /// of the frame is drawn.
+    glyph_cache_full: Option<RenderError>,
+}
+
+fn new_target(facade: &impl Facade, viewport: &Viewport) -> Result<Texture2d, RenderError> {
+    let (width, height) = physical_size(viewport);
+    let target = Texture2d::empty_with_format(
+        facade,
+        UncompressedFloatFormat::U8U8U8U8,
+        MipmapsOption::NoMipmap,
+        width,
+        height,
+    )?;
+    Ok(target)
+}
+
+fn new_multisample_target(
+    facade: &impl Facade,
+    viewport: &Viewport,
+    samples: u32,
+) -> Result<Texture2dMultisample, RenderError> {
+    let (width, height) = physical_size(viewport);
+    let target = Texture2dMultisample::empty_with_format(
+        facade,
+        UncompressedFloatFormat::U8U8U8U8,
+        MipmapsOption::NoMipmap,
+        width,
+        height,
+        samples,
+    )?;
+    Ok(target)
+}
+
+/// The surface to draw frames to, the multisample target when msaa is on.
+fn target_surface<'t>(
+    target: &'t Texture2d,
+    multisample_target: Option<&'t Texture2dMultisample>,
+) -> SimpleFrameBuffer<'t> {
+    match multisample_target {
+        Some(multisample_target) => multisample_target.as_surface(),
+        None => target.as_surface(),
+    }
+}
+
+/// The size of a viewport in whole physical pixels, at least one pixel wide and tall.
+fn physical_size(viewport: &Viewport) -> (u32, u32) {
+    let width = (viewport.width * viewport.scale_factor).round().max(1.0);
+    let height = (viewport.height * viewport.scale_factor).round().max(1.0);
+    (width as u32, height as u32)
+}
+
+impl GliumRenderer {
+    /// Draw to a window.
+    pub fn new(display: &Display) -> Result<Self, RenderError> {
+        let viewport = Viewport::from_display(display);
+        let mut renderer = GliumRenderer::headless(display.get_context().clone(), viewport)?;
+        renderer.display = Some(display.clone());
+        Ok(renderer)
+    }
+
+    /// Draw offscreen into an image the size of a viewport, with a context that might not
+    /// have a window, like one from headless::headless_context.
+    pub fn headless(context: Rc<Context>, viewport: Viewport) -> Result<Self, RenderError> {
+        // Compile shaders for the newest glsl the context has, like glsl 100 on opengl es 2.
+        let shader_version = ShaderVersion::for_context(&context)?;
+        Ok(GliumRenderer {
+            display: None,
+            offscreen_viewport: viewport,
+            target: new_target(&context, &viewport)?,
+            multisample_target: None,
+            msaa: 0,
+            shader_version,
+            quad_renderer: QuadRenderer::new(&context, shader_version)?,
+            glyph_renderer: GlyphRenderer::new(&context, shader_version, viewport.scale_factor)?,
+            outline_renderer: OutlineRenderer::new(&context, shader_version)?,
+            glyph_cache_full: None,
+            context,
+        })
+    }
+
+    /// After RenderError::ContextLost, start drawing to a new window. Everything on the gpu is
+    /// made again in the new window's context, and loaded textures are loaded again so their
+    /// handles still work.
+    pub fn recover(&mut self, display: &Display) -> Result<(), RenderError> {
+        let mut renderer = GliumRenderer::new(display)?;
+        renderer.msaa = self.msaa;
+        for (data, width, height) in self.quad_renderer.texture_images() {
+            renderer.load_texture_rgba(data, width, height)?;
+        }
+        *self = renderer;
+        Ok(())
+    }
+
+    /// Change the size of offscreen frames. Windowed frames are always the window's size.
+    pub fn set_offscreen_viewport(&mut self, viewport: Viewport) {
+        self.offscreen_viewport = viewport;
+    }
+
+    /// The glsl version shaders were compiled as.
+    pub fn shader_version(&self) -> ShaderVersion {
+        self.shader_version
+    }
+
+    /// Antialias edges by drawing with a number of samples per pixel, like 4, or 0 to turn msaa
+    /// off. Takes effect from the next frame.
+    pub fn set_msaa(&mut self, samples: u32) {
+        self.msaa = samples;
+    }
+
+    /// Read the pixels of the last frame drawn, starting at the top left.
+    pub fn read_frame(&self) -> RgbaImage {
+        let image: RawImage2d<u8> = self.target.read();
+        // Opengl textures start at the bottom left, so flip the rows.
+        let row_length = image.width as usize * 4;
+        let data = image
+            .data
+            .chunks_exact(row_length)
+            .rev()
+            .flatten()
+            .copied()
+            .collect();
+        RgbaImage::new(image.width, image.height, data)
+    }
+}
+
+impl Renderer for GliumRenderer {
+    fn viewport(&self) -> Viewport {
+        match &self.display {
+            Some(display) => Viewport::from_display(display),
+            None => self.offscreen_viewport,
+        }
+    }
+
+    fn begin_frame(&mut self, clear_colour: [f32; 4]) -> Result<(), RenderError> {
+        // Drawing does nothing once the context is lost, so stop before drawing a frame.
+        if self.context.is_context_lost() {
+            return Err(RenderError::ContextLost);
+        }
+
+        // Resize the targets when the window was resized, and make or drop the multisample
+        // target when msaa was changed.
+        let viewport = self.viewport();
+        let size = physical_size(&viewport);
+        if size != self.target.dimensions() {
+            self.target = new_target(&self.context, &viewport)?;
+        }
+        let multisample_target_outdated = match &self.multisample_target {
+            Some(target) => self.msaa != target.samples() || size != target.dimensions(),
+            None => self.msaa != 0,
+        };
+        if multisample_target_outdated {
+            self.multisample_target = match self.msaa {
+                0 => None,
+                samples => Some(new_multisample_target(&self.context, &viewport, samples)?),
+            };
+        }
+        let [r, g, b, a] = clear_colour;
+        target_surface(&self.target, self.multisample_target.as_ref()).clear_color(r, g, b, a);
+        Ok(())
+    }
+
+    fn draw_quads(&mut self, quads: &[Quad]) -> Result<(), RenderError> {
+        let viewport = self.viewport();
+        self.quad_renderer.draw(
+            &mut target_surface(&self.target, self.multisample_target.as_ref()),
+            &self.context,
+            &viewport,
+            quads,
+        )
+    }
+
+    fn draw_text(&mut self, runs: &[TextRun]) -> Result<(), RenderError> {
+        let viewport = self.viewport();
+        let drawn = self.glyph_renderer.draw(
+            &mut target_surface(&self.target, self.multisample_target.as_ref()),
+            &self.context,
+            &viewport,
+            runs,
+        );
+        match drawn {
+            // Keep drawing the frame without the glyphs that didn't fit.
+            Err(err @ RenderError::GlyphCacheFull { .. }) => {
+                self.glyph_cache_full = Some(err);
+                Ok(())
+            }
+            drawn => drawn,
+        }
+    }
+
+    fn draw_outlines(&mut self, outlines: &[(Rect<f32>, [f32; 4])]) -> Result<(), RenderError> {
+        let viewport = self.viewport();
+        self.outline_renderer.draw(
+            &mut target_surface(&self.target, self.multisample_target.as_ref()),
+            &self.context,
+            &viewport,
+            outlines,
+        )
+    }
+
+    fn end_frame(&mut self) -> Result<(), RenderError> {
+        // Resolve the samples of each pixel into the target.
+        if let Some(multisample_target) = &self.multisample_target {
+            multisample_target
+                .as_surface()
+                .fill(&self.target.as_surface(), MagnifySamplerFilter::Nearest);
+        }
+
+        // Copy the frame to the window, swap buffers, consume frame.
+        if let Some(display) = &self.display {
+            let frame = display.draw();
+            self.target
+                .as_surface()
+                .fill(&frame, MagnifySamplerFilter::Nearest);
+            frame.finish()?;
+        }
+        match self.glyph_cache_full.take() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    fn load_texture_rgba(
+        &mut self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<TextureHandle, RenderError> {
+        self.quad_renderer
+            .load_texture_rgba(&self.context, data, width, height)
+    }
+}
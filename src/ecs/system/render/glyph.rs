@@ -0,0 +1,454 @@
+use glium::{
+    backend::Facade, implement_vertex, uniform, CapabilitiesSource, Program, Surface, Texture2d,
+    VertexBuffer,
+};
+use rusttype::gpu_cache::{Cache, CacheWriteErr, CachedBy};
+use rusttype::{point, vector, Rect, Vector};
+use std::borrow::Cow;
+
+use super::sdf::{self, SdfGlyphCache, SdfVertex};
+use super::shader::ShaderVersion;
+use super::{reuse_vertex_buffer, RenderError, TextRun};
+use crate::ecs::component::text::GlyphRendering;
+use crate::ecs::component::transform::{Position, Viewport};
+
+/// Draws text runs with glium. Coverage glyphs are rasterized into one cache texture and
+/// sdf glyphs into another, and each is drawn with one vertex buffer and draw call.
+pub struct GlyphRenderer {
+    glyph_cache: Cache<'static>,
+    glyph_cache_texture: Texture2d,
+    shader_program: Program,
+    /// Kept between frames so a new buffer is only made when there's more text than fits.
+    vertex_buffer: Option<VertexBuffer<Vertex>>,
+    /// The scale factor the glyph cache was last sized for.
+    glyph_cache_scale_factor: f32,
+    sdf_cache: SdfGlyphCache,
+    sdf_shader_program: Program,
+    sdf_vertex_buffer: Option<VertexBuffer<SdfVertex>>,
+}
+
+/// The glyph cache's width and height before being scaled by the window's scale factor.
+const GLYPH_CACHE_SIZE: f32 = 512.0;
+
+fn glyph_cache_dimensions(scale_factor: f32) -> (u32, u32) {
+    let size = (GLYPH_CACHE_SIZE * scale_factor) as u32;
+    (size, size)
+}
+
+fn new_glyph_cache_texture(
+    facade: &impl Facade,
+    width: u32,
+    height: u32,
+) -> Result<Texture2d, RenderError> {
+    let texture = Texture2d::with_format(
+        facade,
+        glium::texture::RawImage2d {
+            data: Cow::Owned(vec![128u8; width as usize * height as usize]),
+            width,
+            height,
+            format: glium::texture::ClientFormat::U8,
+        },
+        glium::texture::UncompressedFloatFormat::U8,
+        glium::texture::MipmapsOption::NoMipmap,
+    )?;
+    Ok(texture)
+}
+
+impl GlyphRenderer {
+    /// Make a renderer with its glyph cache sized for a scale factor. The cache is resized
+    /// if the scale factor changes later.
+    pub fn new(
+        facade: &impl Facade,
+        shader_version: ShaderVersion,
+        scale_factor: f32,
+    ) -> Result<Self, RenderError> {
+        // Initialize gpu cache
+        let (cache_width, cache_height) = glyph_cache_dimensions(scale_factor);
+        let glyph_cache: Cache<'static> = Cache::builder()
+            .dimensions(cache_width, cache_height)
+            .build();
+
+        // Initialize shader program.
+        let shader_program = shader_version.program(
+            facade,
+            "
+            IN vec2 position;
+            IN vec2 tex_coords;
+            IN vec4 colour;
+
+            OUT vec2 v_tex_coords;
+            OUT vec4 v_colour;
+
+            void main() {
+                gl_Position = vec4(position, 0.0, 1.0);
+                v_tex_coords = tex_coords;
+                v_colour = colour;
+            }
+        ",
+            "
+            uniform sampler2D tex;
+            IN vec2 v_tex_coords;
+            IN vec4 v_colour;
+
+            void main() {
+                FRAG_COLOUR = v_colour * vec4(1.0, 1.0, 1.0, TEXTURE(tex, v_tex_coords).r);
+            }
+        ",
+        )?;
+
+        // Init gpu cache texture.
+        let glyph_cache_texture = new_glyph_cache_texture(facade, cache_width, cache_height)?;
+
+        Ok(GlyphRenderer {
+            glyph_cache,
+            glyph_cache_texture,
+            shader_program,
+            vertex_buffer: None,
+            glyph_cache_scale_factor: scale_factor,
+            sdf_cache: SdfGlyphCache::new(facade)?,
+            sdf_shader_program: sdf::sdf_shader_program(facade, shader_version)?,
+            sdf_vertex_buffer: None,
+        })
+    }
+
+    /// Upload the images of queued glyphs that aren't in the cache texture yet.
+    fn cache_queued_glyphs(&mut self) -> Result<CachedBy, CacheWriteErr> {
+        let glyph_cache_texture = &self.glyph_cache_texture;
+        self.glyph_cache.cache_queued(|rect, data| {
+            // Write a glyph's image to a part rect in the texture.
+            glyph_cache_texture.main_level().write(
+                glium::Rect {
+                    left: rect.min.x,
+                    bottom: rect.min.y,
+                    width: rect.width(),
+                    height: rect.height(),
+                },
+                glium::texture::RawImage2d {
+                    data: Cow::Borrowed(data),
+                    width: rect.width(),
+                    height: rect.height(),
+                    format: glium::texture::ClientFormat::U8,
+                },
+            );
+        })
+    }
+
+    /// Replace the glyph cache and its texture with empty ones of a new size.
+    fn resize_glyph_cache(
+        &mut self,
+        facade: &impl Facade,
+        width: u32,
+        height: u32,
+    ) -> Result<(), RenderError> {
+        self.glyph_cache
+            .to_builder()
+            .dimensions(width, height)
+            .rebuild(&mut self.glyph_cache);
+        self.glyph_cache_texture = new_glyph_cache_texture(facade, width, height)?;
+        Ok(())
+    }
+
+    /// Queue the glyphs of every run into the caches together, then draw them. Glyphs that
+    /// don't fit in a cache aren't drawn, and give RenderError::GlyphCacheFull once the rest
+    /// are.
+    pub fn draw(
+        &mut self,
+        surface: &mut impl Surface,
+        facade: &impl Facade,
+        viewport: &Viewport,
+        runs: &[TextRun],
+    ) -> Result<(), RenderError> {
+        if runs.iter().all(|run| run.glyphs.is_empty()) {
+            return Ok(());
+        }
+
+        // Size the glyph cache for the current scale factor, like after the window moves to
+        // a monitor with a different dpi.
+        let scale_factor = viewport.scale_factor;
+        if scale_factor != self.glyph_cache_scale_factor {
+            let (width, height) = glyph_cache_dimensions(scale_factor);
+            self.resize_glyph_cache(facade, width, height)?;
+            self.glyph_cache_scale_factor = scale_factor;
+        }
+
+        // Queue all coverage glyphs in the cache. Sdf glyphs have their own cache.
+        for run in runs {
+            if run.effects.rendering == GlyphRendering::Coverage {
+                for (glyph, font) in run.glyphs.iter().zip(&run.fonts) {
+                    self.glyph_cache.queue_glyph(font.index(), glyph.clone());
+                }
+            }
+        }
+        // Sdf glyphs are all added to their atlas before any vertices are made, since making
+        // room moves the glyphs already in it.
+        let sdf_glyphs = runs
+            .iter()
+            .filter(|run| run.effects.rendering == GlyphRendering::Sdf)
+            .flat_map(|run| run.fonts.iter().map(|font| font.index()).zip(&run.glyphs));
+        let mut cache_full = None;
+        match self.sdf_cache.cache_glyphs(facade, sdf_glyphs) {
+            Err(err @ RenderError::GlyphCacheFull { .. }) => cache_full = Some(err),
+            result => result?,
+        }
+        // If the glyphs don't all fit, double the size of the cache and try again. Resizing
+        // clears the cache, but the queue is kept, so every glyph is uploaded again.
+        while self.cache_queued_glyphs().is_err() {
+            let (width, height) = self.glyph_cache.dimensions();
+            let max_size = facade.get_context().get_capabilities().max_texture_size as u32;
+            if width >= max_size && height >= max_size {
+                // Glyphs that didn't fit just aren't drawn this frame.
+                cache_full = Some(RenderError::GlyphCacheFull { width, height });
+                self.glyph_cache.clear_queue();
+                break;
+            }
+            self.resize_glyph_cache(
+                facade,
+                (width * 2).min(max_size),
+                (height * 2).min(max_size),
+            )?;
+        }
+
+        // Build the vertices for every glyph's rect including position, texture coordinates,
+        // and colour. Shadows are added before their glyph so they're drawn underneath it.
+        let mut vertices: Vec<Vertex> = Vec::new();
+        let mut sdf_vertices: Vec<SdfVertex> = Vec::new();
+        for run in runs {
+            let shadow = run.effects.shadow.map(|shadow| {
+                let offset = vector(shadow.offset.x, shadow.offset.y) * viewport.scale_factor;
+                (offset, shadow.colour)
+            });
+            for (glyph, font) in run.glyphs.iter().zip(&run.fonts) {
+                match run.effects.rendering {
+                    GlyphRendering::Coverage => {
+                        // uv_rect is where the glyph is in the cache texture
+                        // screen_rect is where the glyph is going to be drawn on the screen in
+                        // pixel space
+                        let (uv_rect, screen_rect) =
+                            match self.glyph_cache.rect_for(font.index(), glyph) {
+                                Ok(Some(rects)) => rects,
+                                _ => continue,
+                            };
+                        let screen_rect = Rect {
+                            min: point(screen_rect.min.x as f32, screen_rect.min.y as f32),
+                            max: point(screen_rect.max.x as f32, screen_rect.max.y as f32),
+                        };
+                        let mut add_quad = |screen_rect, colour| {
+                            let quad = glyph_quad(screen_rect, uv_rect, viewport);
+                            vertices.extend(quad.map(|(position, tex_coords)| Vertex {
+                                position,
+                                tex_coords,
+                                colour,
+                            }));
+                        };
+                        if let Some((offset, colour)) = shadow {
+                            add_quad(moved_rect(screen_rect, offset), colour);
+                        }
+                        add_quad(screen_rect, run.colour);
+                    }
+                    GlyphRendering::Sdf => {
+                        let (uv_rect, screen_rect) =
+                            match self.sdf_cache.rect_for(font.index(), glyph) {
+                                Some(rects) => rects,
+                                None => continue,
+                            };
+                        // Convert the outline's width from logical pixels to the distance
+                        // field's units, where SDF_SPREAD pixels at SDF_GLYPH_SIZE is 0.5.
+                        let (outline_width, outline_colour) = match run.effects.outline {
+                            Some(outline) => {
+                                let ratio = glyph.scale().y / sdf::SDF_GLYPH_SIZE;
+                                let sdf_pixels = outline.width * viewport.scale_factor / ratio;
+                                (sdf_pixels / (2.0 * sdf::SDF_SPREAD), outline.colour)
+                            }
+                            None => (0.0, run.colour),
+                        };
+                        let mut add_quad = |screen_rect, colour, outline_colour| {
+                            let quad = glyph_quad(screen_rect, uv_rect, viewport);
+                            sdf_vertices.extend(quad.map(|(position, tex_coords)| SdfVertex {
+                                position,
+                                tex_coords,
+                                colour,
+                                outline_colour,
+                                outline_width,
+                            }));
+                        };
+                        if let Some((offset, colour)) = shadow {
+                            add_quad(moved_rect(screen_rect, offset), colour, colour);
+                        }
+                        add_quad(screen_rect, run.colour, outline_colour);
+                    }
+                }
+            }
+        }
+
+        let draw_parameters = glium::DrawParameters {
+            blend: glium::Blend::alpha_blending(),
+            ..Default::default()
+        };
+
+        // Draw every coverage glyph's vertices with uniforms and shader program
+        if !vertices.is_empty() {
+            let uniforms = uniform! {
+                tex: self.glyph_cache_texture.sampled().magnify_filter(glium::uniforms::MagnifySamplerFilter::Nearest)
+            };
+            let vertex_buffer = reuse_vertex_buffer(&mut self.vertex_buffer, facade, &vertices)?;
+            surface.draw(
+                vertex_buffer,
+                glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
+                &self.shader_program,
+                &uniforms,
+                &draw_parameters,
+            )?;
+        }
+
+        // Draw every sdf glyph. Distance fields are filtered linearly so edges between texels
+        // stay smooth when scaled up.
+        if !sdf_vertices.is_empty() {
+            let uniforms = uniform! {
+                tex: self.sdf_cache.texture().sampled()
+                    .magnify_filter(glium::uniforms::MagnifySamplerFilter::Linear)
+                    .minify_filter(glium::uniforms::MinifySamplerFilter::Linear)
+            };
+            let vertex_buffer =
+                reuse_vertex_buffer(&mut self.sdf_vertex_buffer, facade, &sdf_vertices)?;
+            surface.draw(
+                vertex_buffer,
+                glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
+                &self.sdf_shader_program,
+                &uniforms,
+                &draw_parameters,
+            )?;
+        }
+        match cache_full {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+fn moved_rect(rect: Rect<f32>, offset: Vector<f32>) -> Rect<f32> {
+    Rect {
+        min: rect.min + offset,
+        max: rect.max + offset,
+    }
+}
+
+/// The positions and texture coordinates of two triangles forming a rectangle around a glyph.
+fn glyph_quad(
+    screen_rect: Rect<f32>,
+    uv_rect: Rect<f32>,
+    viewport: &Viewport,
+) -> [([f32; 2], [f32; 2]); 6] {
+    // This converts the pixel-space coordinate system of the glyphs to opengl's.
+    let to_gl = |x, y| {
+        let [x, y] = viewport.to_gl(Position { x, y });
+        point(x, y)
+    };
+    let gl_rect = Rect {
+        min: to_gl(screen_rect.min.x, screen_rect.min.y),
+        max: to_gl(screen_rect.max.x, screen_rect.max.y),
+    };
+    [
+        (
+            [gl_rect.min.x, gl_rect.max.y],
+            [uv_rect.min.x, uv_rect.max.y],
+        ),
+        (
+            [gl_rect.min.x, gl_rect.min.y],
+            [uv_rect.min.x, uv_rect.min.y],
+        ),
+        (
+            [gl_rect.max.x, gl_rect.min.y],
+            [uv_rect.max.x, uv_rect.min.y],
+        ),
+        (
+            [gl_rect.max.x, gl_rect.min.y],
+            [uv_rect.max.x, uv_rect.min.y],
+        ),
+        (
+            [gl_rect.max.x, gl_rect.max.y],
+            [uv_rect.max.x, uv_rect.max.y],
+        ),
+        (
+            [gl_rect.min.x, gl_rect.max.y],
+            [uv_rect.min.x, uv_rect.max.y],
+        ),
+    ]
+}
+
+#[derive(Copy, Clone)]
+struct Vertex {
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+    colour: [f32; 4],
+}
+implement_vertex!(Vertex, position, tex_coords, colour);
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::ecs::system::render::headless::headless_context;
+    use crate::ecs::system::text::font::FontHandle;
+    use glium::texture::RawImage2d;
+    use rusttype::{Font, Scale};
+
+    const ROBOTO: &[u8] = include_bytes!("../../../Roboto-Regular.ttf");
+
+    #[test]
+    fn a_tiny_glyph_cache_grows_until_the_text_fits() {
+        let context = match headless_context() {
+            Ok(context) => context,
+            Err(err) => {
+                eprintln!("skipping, {}", err);
+                return;
+            }
+        };
+        let shader_version = ShaderVersion::for_context(&context).unwrap();
+        let mut renderer = GlyphRenderer::new(&context, shader_version, 1.0).unwrap();
+        renderer.resize_glyph_cache(&context, 32, 32).unwrap();
+
+        let font = Font::try_from_bytes(ROBOTO).unwrap();
+        let mut x = 10.0;
+        let glyphs: Vec<_> = "Simon Says"
+            .chars()
+            .map(|c| {
+                let glyph = font.glyph(c).scaled(Scale::uniform(48.0));
+                let advance_width = glyph.h_metrics().advance_width;
+                let positioned = glyph.positioned(point(x, 60.0));
+                x += advance_width;
+                positioned
+            })
+            .collect();
+        let run = TextRun {
+            fonts: vec![FontHandle::default(); glyphs.len()],
+            glyphs,
+            colour: [1.0, 1.0, 1.0, 1.0],
+            effects: Default::default(),
+        };
+        let viewport = Viewport {
+            width: 256.0,
+            height: 128.0,
+            scale_factor: 1.0,
+        };
+        let target = Texture2d::empty(&context, 256, 128).unwrap();
+        let mut surface = target.as_surface();
+        surface.clear_color(0.0, 0.0, 0.0, 0.0);
+        renderer
+            .draw(&mut surface, &context, &viewport, &[run])
+            .unwrap();
+
+        let (width, height) = renderer.glyph_cache.dimensions();
+        assert!(
+            width > 32 && height > 32,
+            "cache is still {}x{}",
+            width,
+            height
+        );
+        let image: RawImage2d<u8> = target.read();
+        let drawn = image
+            .data
+            .chunks_exact(4)
+            .filter(|pixel| pixel[3] > 0)
+            .count();
+        assert!(drawn > 500, "only {} pixels were drawn", drawn);
+    }
+}
@@ -0,0 +1,144 @@
+use glium::backend::{Backend, Context, Facade};
+use glium::debug::DebugCallbackBehavior;
+use glium::glutin::dpi::PhysicalSize;
+use glium::glutin::platform::unix::HeadlessContextExt;
+use glium::{IncompatibleOpenGl, SwapBuffersError};
+use khronos_egl as egl;
+use std::ffi::c_void;
+use std::fmt;
+use std::rc::Rc;
+
+#[derive(Debug)]
+pub enum HeadlessError {
+    /// Neither an egl display without a window nor osmesa could make a context. Has the reason
+    /// each one failed.
+    NoContext { egl: String, osmesa: String },
+    /// A context was made, but its opengl version is too old for glium.
+    IncompatibleOpenGl(IncompatibleOpenGl),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeadlessError::NoContext { egl, osmesa } => write!(
+                f,
+                "could not make an opengl context without a window (egl: {}, osmesa: {})",
+                egl, osmesa
+            ),
+            HeadlessError::IncompatibleOpenGl(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+impl From<IncompatibleOpenGl> for HeadlessError {
+    fn from(err: IncompatibleOpenGl) -> Self {
+        HeadlessError::IncompatibleOpenGl(err)
+    }
+}
+
+/// Make an opengl context that doesn't need a window or display server, for drawing offscreen
+/// with GliumRenderer::headless. Mesa's egl is tried first, which uses the gpu or falls back to
+/// llvmpipe in software, then osmesa.
+pub fn headless_context() -> Result<Rc<Context>, HeadlessError> {
+    let egl_err = match EglBackend::new() {
+        Ok(backend) => {
+            return Ok(unsafe { Context::new(backend, true, DebugCallbackBehavior::Ignore)? })
+        }
+        Err(err) => err,
+    };
+    // Osmesa draws to its own buffer, but glium only draws offscreen into textures here, so
+    // its size doesn't matter.
+    match glium::glutin::ContextBuilder::new().build_osmesa(PhysicalSize::new(1, 1)) {
+        Ok(context) => Ok(glium::HeadlessRenderer::new(context)?.get_context().clone()),
+        Err(err) => Err(HeadlessError::NoContext {
+            egl: egl_err,
+            osmesa: err.to_string(),
+        }),
+    }
+}
+
+/// From EGL_MESA_platform_surfaceless, a display that isn't connected to a window system.
+const PLATFORM_SURFACELESS_MESA: egl::Enum = 0x31DD;
+
+/// An egl context with no surface. There's no default framebuffer, everything is drawn into
+/// textures.
+struct EglBackend {
+    egl: egl::DynamicInstance<egl::EGL1_5>,
+    display: egl::Display,
+    context: egl::Context,
+}
+
+impl EglBackend {
+    fn new() -> Result<Self, String> {
+        let egl = unsafe { egl::DynamicInstance::<egl::EGL1_5>::load_required() }
+            .map_err(|err| err.to_string())?;
+        let display = egl
+            .get_platform_display(
+                PLATFORM_SURFACELESS_MESA,
+                std::ptr::null_mut(),
+                &[egl::ATTRIB_NONE],
+            )
+            .map_err(|err| err.to_string())?;
+        egl.initialize(display).map_err(|err| err.to_string())?;
+
+        // Surfaceless displays only have configs for pbuffers, not windows.
+        let config_attributes = [
+            egl::RENDERABLE_TYPE,
+            egl::OPENGL_BIT,
+            egl::SURFACE_TYPE,
+            egl::PBUFFER_BIT,
+            egl::NONE,
+        ];
+        let config = egl
+            .choose_first_config(display, &config_attributes)
+            .map_err(|err| err.to_string())?
+            .ok_or("no config for desktop opengl")?;
+        egl.bind_api(egl::OPENGL_API)
+            .map_err(|err| err.to_string())?;
+        let context = egl
+            .create_context(display, config, None, &[egl::NONE])
+            .map_err(|err| err.to_string())?;
+        egl.make_current(display, None, None, Some(context))
+            .map_err(|err| err.to_string())?;
+        Ok(EglBackend {
+            egl,
+            display,
+            context,
+        })
+    }
+}
+
+unsafe impl Backend for EglBackend {
+    fn swap_buffers(&self) -> Result<(), SwapBuffersError> {
+        Ok(())
+    }
+
+    unsafe fn get_proc_address(&self, symbol: &str) -> *const c_void {
+        self.egl
+            .get_proc_address(symbol)
+            .map_or(std::ptr::null(), |address| address as *const c_void)
+    }
+
+    fn get_framebuffer_dimensions(&self) -> (u32, u32) {
+        (0, 0)
+    }
+
+    fn is_current(&self) -> bool {
+        self.egl.get_current_context() == Some(self.context)
+    }
+
+    unsafe fn make_current(&self) {
+        self.egl
+            .make_current(self.display, None, None, Some(self.context))
+            .unwrap();
+    }
+}
+
+impl Drop for EglBackend {
+    fn drop(&mut self) {
+        let _ = self.egl.destroy_context(self.display, self.context);
+        let _ = self.egl.terminate(self.display);
+    }
+}
@@ -0,0 +1,164 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum ImageError {
+    /// The image file couldn't be read from or written to disk.
+    Io(std::io::Error),
+    /// The file wasn't a png the decoder could read.
+    Decode(png::DecodingError),
+    Encode(png::EncodingError),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::Io(err) => write!(f, "could not access image file: {}", err),
+            ImageError::Decode(err) => write!(f, "could not decode png: {}", err),
+            ImageError::Encode(err) => write!(f, "could not encode png: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ImageError::Io(err) => Some(err),
+            ImageError::Decode(err) => Some(err),
+            ImageError::Encode(err) => Some(err),
+        }
+    }
+}
+
+impl From<std::io::Error> for ImageError {
+    fn from(err: std::io::Error) -> Self {
+        ImageError::Io(err)
+    }
+}
+
+impl From<png::DecodingError> for ImageError {
+    fn from(err: png::DecodingError) -> Self {
+        ImageError::Decode(err)
+    }
+}
+
+impl From<png::EncodingError> for ImageError {
+    fn from(err: png::EncodingError) -> Self {
+        ImageError::Encode(err)
+    }
+}
+
+/// An image with 4 bytes of rgba per pixel, starting at the top left like png files and
+/// load_texture_rgba.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl RgbaImage {
+    pub fn new(width: u32, height: u32, data: Vec<u8>) -> Self {
+        assert_eq!(data.len(), width as usize * height as usize * 4);
+        RgbaImage {
+            width,
+            height,
+            data,
+        }
+    }
+
+    /// The rgba of the pixel x pixels from the left and y pixels from the top.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        [
+            self.data[i],
+            self.data[i + 1],
+            self.data[i + 2],
+            self.data[i + 3],
+        ]
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), ImageError> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.data)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Load a png of any colour type, converting it to 8 bit rgba.
+    pub fn load_png(path: impl AsRef<Path>) -> Result<Self, ImageError> {
+        let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+        // Expand palettes and low bit depths, and strip 16 bit channels to 8 bits.
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+        buffer.truncate(info.buffer_size());
+
+        let data = match info.color_type {
+            png::ColorType::Rgba => buffer,
+            png::ColorType::Rgb => buffer
+                .chunks_exact(3)
+                .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buffer
+                .chunks_exact(2)
+                .flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]])
+                .collect(),
+            png::ColorType::Grayscale => buffer.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+            // Palettes are expanded to rgb or rgba by the transformations.
+            png::ColorType::Indexed => unreachable!(),
+        };
+        Ok(RgbaImage::new(info.width, info.height, data))
+    }
+
+    /// Compare each pixel with another image's. Pixels are only counted as different when one
+    /// of their channels differs by more than channel_tolerance, so small differences between
+    /// gpus and drivers, like how edges are antialiased, can be ignored.
+    pub fn compare(&self, other: &RgbaImage, channel_tolerance: u8) -> ImageDifference {
+        if (self.width, self.height) != (other.width, other.height) {
+            return ImageDifference {
+                same_size: false,
+                different_pixels: self.width as usize * self.height as usize,
+                max_channel_difference: u8::MAX,
+            };
+        }
+        let mut different_pixels = 0;
+        let mut max_channel_difference = 0;
+        for (a, b) in self.data.chunks_exact(4).zip(other.data.chunks_exact(4)) {
+            let difference = a.iter().zip(b).map(|(a, b)| a.abs_diff(*b)).max().unwrap();
+            max_channel_difference = max_channel_difference.max(difference);
+            if difference > channel_tolerance {
+                different_pixels += 1;
+            }
+        }
+        ImageDifference {
+            same_size: true,
+            different_pixels,
+            max_channel_difference,
+        }
+    }
+}
+
+/// How different two images are, from RgbaImage::compare.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ImageDifference {
+    /// Images of different sizes aren't compared, every pixel is counted as different.
+    pub same_size: bool,
+    /// How many pixels had a channel differ by more than the tolerance.
+    pub different_pixels: usize,
+    pub max_channel_difference: u8,
+}
+
+impl ImageDifference {
+    /// Whether the images are the same size and at most max_different_pixels pixels differ.
+    pub fn matches(&self, max_different_pixels: usize) -> bool {
+        self.same_size && self.different_pixels <= max_different_pixels
+    }
+}
@@ -0,0 +1,86 @@
+use glium::{backend::Facade, implement_vertex, Program, Surface};
+use rusttype::Rect;
+
+use super::shader::ShaderVersion;
+use super::RenderError;
+use crate::ecs::component::transform::{Position, Viewport};
+
+/// Draws rects as one pixel outlines, for seeing where text and transforms are while debugging.
+pub struct OutlineRenderer {
+    shader_program: Program,
+}
+
+impl OutlineRenderer {
+    pub fn new(facade: &impl Facade, shader_version: ShaderVersion) -> Result<Self, RenderError> {
+        let shader_program = shader_version.program(
+            facade,
+            "
+            IN vec2 position;
+            IN vec4 colour;
+
+            OUT vec4 v_colour;
+
+            void main() {
+                gl_Position = vec4(position, 0.0, 1.0);
+                v_colour = colour;
+            }
+        ",
+            "
+            IN vec4 v_colour;
+
+            void main() {
+                FRAG_COLOUR = v_colour;
+            }
+        ",
+        )?;
+        Ok(OutlineRenderer { shader_program })
+    }
+
+    /// Draw the outline of each rect, given in physical pixels from the top left of the window.
+    pub fn draw(
+        &self,
+        surface: &mut impl Surface,
+        facade: &impl Facade,
+        viewport: &Viewport,
+        rects: &[(Rect<f32>, [f32; 4])],
+    ) -> Result<(), RenderError> {
+        if rects.is_empty() {
+            return Ok(());
+        }
+        let to_gl = |x: f32, y: f32| viewport.to_gl(Position { x, y });
+        let vertices: Vec<OutlineVertex> = rects
+            .iter()
+            .flat_map(|(rect, colour)| {
+                let corners = [
+                    to_gl(rect.min.x, rect.min.y),
+                    to_gl(rect.max.x, rect.min.y),
+                    to_gl(rect.max.x, rect.max.y),
+                    to_gl(rect.min.x, rect.max.y),
+                ];
+                // Each pair of vertices is one side of the rect.
+                (0..4).flat_map(move |i| {
+                    [corners[i], corners[(i + 1) % 4]].map(|position| OutlineVertex {
+                        position,
+                        colour: *colour,
+                    })
+                })
+            })
+            .collect();
+        let vertex_buffer = glium::VertexBuffer::new(facade, &vertices)?;
+        surface.draw(
+            &vertex_buffer,
+            glium::index::NoIndices(glium::index::PrimitiveType::LinesList),
+            &self.shader_program,
+            &glium::uniforms::EmptyUniforms,
+            &Default::default(),
+        )?;
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone)]
+struct OutlineVertex {
+    position: [f32; 2],
+    colour: [f32; 4],
+}
+implement_vertex!(OutlineVertex, position, colour);
@@ -0,0 +1,201 @@
+use glium::{
+    backend::Facade, implement_vertex, uniform, Program, Surface, Texture2d, VertexBuffer,
+};
+
+use super::shader::ShaderVersion;
+use super::{reuse_vertex_buffer, Quad, RenderError, TextureHandle};
+use crate::ecs::component::transform::{Position, Viewport};
+
+/// Draws quads as colored or textured shapes with glium. Quads sharing a texture are drawn
+/// together, in order, so later quads are drawn over earlier ones.
+pub struct QuadRenderer {
+    shader_program: Program,
+    textures: Vec<Texture2d>,
+    /// The rgba pixels and size each texture was loaded from, to load them again into a new
+    /// context after the old one was lost.
+    texture_images: Vec<(Vec<u8>, u32, u32)>,
+    /// Sampled by quads without a texture, so they're filled with their colour.
+    white_texture: Texture2d,
+    vertex_buffer: Option<VertexBuffer<QuadVertex>>,
+}
+
+impl QuadRenderer {
+    pub fn new(facade: &impl Facade, shader_version: ShaderVersion) -> Result<Self, RenderError> {
+        let shader_program = shader_version.program(
+            facade,
+            "
+            IN vec2 position;
+            IN vec2 tex_coords;
+            IN vec4 colour;
+            IN vec2 local_position;
+            IN vec2 half_size;
+            IN float radius;
+
+            OUT vec2 v_tex_coords;
+            OUT vec4 v_colour;
+            OUT vec2 v_local_position;
+            OUT vec2 v_half_size;
+            OUT float v_radius;
+
+            void main() {
+                gl_Position = vec4(position, 0.0, 1.0);
+                v_tex_coords = tex_coords;
+                v_colour = colour;
+                v_local_position = local_position;
+                v_half_size = half_size;
+                v_radius = radius;
+            }
+        ",
+            "
+            uniform sampler2D tex;
+            IN vec2 v_tex_coords;
+            IN vec4 v_colour;
+            IN vec2 v_local_position;
+            IN vec2 v_half_size;
+            IN float v_radius;
+
+            void main() {
+                // Distance in pixels from the edge of a rounded rectangle, negative inside.
+                // Rectangles have no radius and circles have a radius of half their size.
+                vec2 q = abs(v_local_position) - v_half_size + v_radius;
+                float edge_distance = length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - v_radius;
+                float coverage = clamp(0.5 - edge_distance, 0.0, 1.0);
+                vec4 colour = v_colour * TEXTURE(tex, v_tex_coords);
+                FRAG_COLOUR = vec4(colour.rgb, colour.a * coverage);
+            }
+        ",
+        )?;
+        let white_texture = Texture2d::new(
+            facade,
+            glium::texture::RawImage2d::from_raw_rgba(vec![255u8; 4], (1, 1)),
+        )?;
+        Ok(QuadRenderer {
+            shader_program,
+            textures: Vec::new(),
+            texture_images: Vec::new(),
+            white_texture,
+            vertex_buffer: None,
+        })
+    }
+
+    /// Load an image from its pixels, 4 bytes of rgba per pixel, starting at the top left.
+    pub fn load_texture_rgba(
+        &mut self,
+        facade: &impl Facade,
+        data: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<TextureHandle, RenderError> {
+        // Opengl textures start at the bottom left, so flip the rows.
+        let image = glium::texture::RawImage2d::from_raw_rgba_reversed(data, (width, height));
+        self.textures.push(Texture2d::new(facade, image)?);
+        self.texture_images.push((data.to_vec(), width, height));
+        Ok(TextureHandle(self.textures.len() - 1))
+    }
+
+    /// The pixels, width, and height of every texture loaded, in the order they were loaded.
+    pub fn texture_images(&self) -> impl Iterator<Item = (&[u8], u32, u32)> {
+        self.texture_images
+            .iter()
+            .map(|(data, width, height)| (data.as_slice(), *width, *height))
+    }
+
+    /// Draw quads in order, with one draw call for each run of quads using the same texture.
+    pub fn draw(
+        &mut self,
+        surface: &mut impl Surface,
+        facade: &impl Facade,
+        viewport: &Viewport,
+        quads: &[Quad],
+    ) -> Result<(), RenderError> {
+        let mut batches: Vec<(Option<TextureHandle>, Vec<QuadVertex>)> = Vec::new();
+        for quad in quads {
+            let vertices = quad_vertices(quad, viewport);
+            match batches.last_mut() {
+                Some((texture, batch)) if *texture == quad.texture => batch.extend(vertices),
+                _ => batches.push((quad.texture, vertices.to_vec())),
+            }
+        }
+
+        let draw_parameters = glium::DrawParameters {
+            blend: glium::Blend::alpha_blending(),
+            ..Default::default()
+        };
+        for (texture, vertices) in batches {
+            let texture = texture
+                .and_then(|texture| self.textures.get(texture.index()))
+                .unwrap_or(&self.white_texture);
+            let uniforms = uniform! {
+                tex: texture.sampled()
+                    .magnify_filter(glium::uniforms::MagnifySamplerFilter::Linear)
+                    .minify_filter(glium::uniforms::MinifySamplerFilter::Linear)
+            };
+            let vertex_buffer = reuse_vertex_buffer(&mut self.vertex_buffer, facade, &vertices)?;
+            surface.draw(
+                vertex_buffer,
+                glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
+                &self.shader_program,
+                &uniforms,
+                &draw_parameters,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Two triangles covering a quad.
+fn quad_vertices(quad: &Quad, viewport: &Viewport) -> [QuadVertex; 6] {
+    let half_size = [quad.rect.width() / 2.0, quad.rect.height() / 2.0];
+    let center = Position {
+        x: quad.rect.min.x + half_size[0],
+        y: quad.rect.min.y + half_size[1],
+    };
+    let radius = quad.radius.clamp(0.0, half_size[0].min(half_size[1]));
+    // Each corner's offset from the center, and its texture coordinates.
+    let corner = |x: f32, y: f32| {
+        let local_position = [x * half_size[0], y * half_size[1]];
+        QuadVertex {
+            position: viewport.to_gl(Position {
+                x: center.x + local_position[0],
+                y: center.y + local_position[1],
+            }),
+            tex_coords: [(x + 1.0) / 2.0, (1.0 - y) / 2.0],
+            colour: quad.colour,
+            local_position,
+            half_size,
+            radius,
+        }
+    };
+    let top_left = corner(-1.0, -1.0);
+    let top_right = corner(1.0, -1.0);
+    let bottom_right = corner(1.0, 1.0);
+    let bottom_left = corner(-1.0, 1.0);
+    [
+        bottom_left,
+        top_left,
+        top_right,
+        top_right,
+        bottom_right,
+        bottom_left,
+    ]
+}
+
+#[derive(Copy, Clone)]
+struct QuadVertex {
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+    colour: [f32; 4],
+    /// Position from the shape's center in physical pixels, for finding its edge.
+    local_position: [f32; 2],
+    half_size: [f32; 2],
+    radius: f32,
+}
+implement_vertex!(
+    QuadVertex,
+    position,
+    tex_coords,
+    colour,
+    local_position,
+    half_size,
+    radius
+);
@@ -0,0 +1,115 @@
+use rusttype::Rect;
+
+use super::{Quad, RenderError, Renderer, TextRun, TextureHandle};
+use crate::ecs::component::transform::Viewport;
+
+/// Something a RecordingRenderer was asked to do.
+#[derive(Clone, Debug)]
+pub enum DrawCommand {
+    BeginFrame { clear_colour: [f32; 4] },
+    Quads(Vec<Quad>),
+    Text(Vec<TextRun>),
+    Outlines(Vec<(Rect<f32>, [f32; 4])>),
+    EndFrame,
+    LoadTexture { width: u32, height: u32 },
+}
+
+/// A renderer that doesn't draw anything, it only remembers what it was asked to draw. It
+/// needs no window or gpu, so systems can be checked in tests and on CI by looking at the
+/// commands they made.
+pub struct RecordingRenderer {
+    viewport: Viewport,
+    commands: Vec<DrawCommand>,
+    texture_count: usize,
+}
+
+impl RecordingRenderer {
+    /// Record drawing to a viewport, like Viewport { width: 512.0, height: 512.0,
+    /// scale_factor: 1.0 } for the default window.
+    pub fn new(viewport: Viewport) -> Self {
+        RecordingRenderer {
+            viewport,
+            commands: Vec::new(),
+            texture_count: 0,
+        }
+    }
+
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+    }
+
+    pub fn commands(&self) -> &[DrawCommand] {
+        &self.commands
+    }
+
+    /// Take the recorded commands, leaving none recorded.
+    pub fn take_commands(&mut self) -> Vec<DrawCommand> {
+        std::mem::take(&mut self.commands)
+    }
+
+    /// Every quad drawn, in the order they were drawn.
+    pub fn quads(&self) -> impl Iterator<Item = &Quad> {
+        self.commands.iter().flat_map(|command| match command {
+            DrawCommand::Quads(quads) => quads.as_slice(),
+            _ => &[],
+        })
+    }
+
+    /// Every text run drawn, in the order they were drawn.
+    pub fn text_runs(&self) -> impl Iterator<Item = &TextRun> {
+        self.commands.iter().flat_map(|command| match command {
+            DrawCommand::Text(runs) => runs.as_slice(),
+            _ => &[],
+        })
+    }
+}
+
+impl Renderer for RecordingRenderer {
+    fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    fn begin_frame(&mut self, clear_colour: [f32; 4]) -> Result<(), RenderError> {
+        self.commands.push(DrawCommand::BeginFrame { clear_colour });
+        Ok(())
+    }
+
+    fn draw_quads(&mut self, quads: &[Quad]) -> Result<(), RenderError> {
+        if !quads.is_empty() {
+            self.commands.push(DrawCommand::Quads(quads.to_vec()));
+        }
+        Ok(())
+    }
+
+    fn draw_text(&mut self, runs: &[TextRun]) -> Result<(), RenderError> {
+        if !runs.is_empty() {
+            self.commands.push(DrawCommand::Text(runs.to_vec()));
+        }
+        Ok(())
+    }
+
+    fn draw_outlines(&mut self, outlines: &[(Rect<f32>, [f32; 4])]) -> Result<(), RenderError> {
+        if !outlines.is_empty() {
+            self.commands.push(DrawCommand::Outlines(outlines.to_vec()));
+        }
+        Ok(())
+    }
+
+    fn end_frame(&mut self) -> Result<(), RenderError> {
+        self.commands.push(DrawCommand::EndFrame);
+        Ok(())
+    }
+
+    fn load_texture_rgba(
+        &mut self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<TextureHandle, RenderError> {
+        debug_assert_eq!(data.len(), width as usize * height as usize * 4);
+        self.commands
+            .push(DrawCommand::LoadTexture { width, height });
+        self.texture_count += 1;
+        Ok(TextureHandle(self.texture_count - 1))
+    }
+}
@@ -0,0 +1,381 @@
+use glium::{backend::Facade, implement_vertex, CapabilitiesSource, Program, Texture2d};
+use rusttype::{point, Font, GlyphId, PositionedGlyph, Rect, Scale};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use super::shader::ShaderVersion;
+use super::RenderError;
+
+/// Glyphs are rasterized at this size before being turned into distance fields. Bigger text is
+/// drawn by scaling the distance field up, which stays sharp unlike scaling a coverage bitmap.
+pub const SDF_GLYPH_SIZE: f32 = 64.0;
+/// How many pixels out from a glyph's edge the distance field reaches. This also limits how
+/// wide an outline can be.
+pub const SDF_SPREAD: f32 = 8.0;
+/// The atlas's width and height before it has to grow.
+const SDF_ATLAS_SIZE: u32 = 1024;
+
+/// Turn a glyph's coverage bitmap into a signed distance field. Each pixel stores how far it is
+/// from the glyph's edge, mapped so 0.5 is on the edge, above 0.5 is inside and below is outside.
+pub fn distance_field(coverage: &[f32], width: usize, height: usize, spread: f32) -> Vec<u8> {
+    let radius = spread.ceil() as isize;
+    let is_inside = |x: isize, y: isize| {
+        x >= 0
+            && y >= 0
+            && (x as usize) < width
+            && (y as usize) < height
+            && coverage[y as usize * width + x as usize] >= 0.5
+    };
+    let mut field = Vec::with_capacity(width * height);
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            let inside = is_inside(x, y);
+            // Find the closest pixel on the other side of the edge.
+            let mut closest = spread;
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    if is_inside(x + dx, y + dy) != inside {
+                        let distance = ((dx * dx + dy * dy) as f32).sqrt();
+                        closest = closest.min(distance);
+                    }
+                }
+            }
+            let signed = if inside { closest } else { -closest };
+            let value = (0.5 + signed / (2.0 * spread)).clamp(0.0, 1.0);
+            field.push((value * 255.0) as u8);
+        }
+    }
+    field
+}
+
+struct SdfGlyph {
+    /// Where the glyph's distance field is in the atlas, in texture coordinates.
+    uv_rect: Rect<f32>,
+    /// The distance field's rect around the glyph's origin, in pixels at SDF_GLYPH_SIZE.
+    bounds: Rect<f32>,
+}
+
+/// Keeps the distance fields of glyphs drawn with GlyphRendering::Sdf in one texture atlas.
+/// Glyphs are packed in rows. When the atlas fills up it's doubled in size and refilled, up to
+/// the biggest texture the gpu supports, after which it's cleared and refilled instead.
+pub struct SdfGlyphCache {
+    texture: Texture2d,
+    /// The atlas's width and height.
+    size: u32,
+    glyphs: HashMap<(usize, GlyphId), SdfGlyph>,
+    // Where the next glyph goes in the atlas.
+    cursor_x: u32,
+    cursor_y: u32,
+    row_height: u32,
+}
+
+/// There's no room left in the atlas for a glyph.
+struct AtlasFull;
+
+fn new_atlas_texture(facade: &impl Facade, size: u32) -> Result<Texture2d, RenderError> {
+    let texture = Texture2d::with_format(
+        facade,
+        glium::texture::RawImage2d {
+            data: Cow::Owned(vec![0u8; size as usize * size as usize]),
+            width: size,
+            height: size,
+            format: glium::texture::ClientFormat::U8,
+        },
+        glium::texture::UncompressedFloatFormat::U8,
+        glium::texture::MipmapsOption::NoMipmap,
+    )?;
+    Ok(texture)
+}
+
+impl SdfGlyphCache {
+    pub fn new(facade: &impl Facade) -> Result<Self, RenderError> {
+        Ok(SdfGlyphCache {
+            texture: new_atlas_texture(facade, SDF_ATLAS_SIZE)?,
+            size: SDF_ATLAS_SIZE,
+            glyphs: HashMap::new(),
+            cursor_x: 0,
+            cursor_y: 0,
+            row_height: 0,
+        })
+    }
+
+    pub fn texture(&self) -> &Texture2d {
+        &self.texture
+    }
+
+    /// The atlas's width and height.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Make the distance fields of glyphs that aren't in the atlas yet. Every glyph of a frame
+    /// has to be added before any are drawn, since making room moves the glyphs already there.
+    /// Glyphs that still don't fit aren't drawn, and give RenderError::GlyphCacheFull once the
+    /// others are cached.
+    pub fn cache_glyphs<'g>(
+        &mut self,
+        facade: &impl Facade,
+        glyphs: impl Iterator<Item = (usize, &'g PositionedGlyph<'g>)> + Clone,
+    ) -> Result<(), RenderError> {
+        let max_size = facade.get_context().get_capabilities().max_texture_size as u32;
+        let mut cleared = false;
+        while self.add_glyphs(glyphs.clone()).is_err() {
+            if self.size < max_size {
+                self.resize(facade, (self.size * 2).min(max_size))?;
+            } else if !cleared {
+                // Make room by dropping glyphs from earlier frames.
+                self.resize(facade, self.size)?;
+                cleared = true;
+            } else {
+                return Err(RenderError::GlyphCacheFull {
+                    width: self.size,
+                    height: self.size,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Get where a glyph is in the atlas, and where its distance field goes on the screen in
+    /// pixels. Returns None for glyphs that weren't cached, and glyphs with nothing to draw,
+    /// like spaces.
+    pub fn rect_for(
+        &self,
+        font_id: usize,
+        glyph: &PositionedGlyph,
+    ) -> Option<(Rect<f32>, Rect<f32>)> {
+        let sdf_glyph = self.glyphs.get(&(font_id, glyph.id()))?;
+        let ratio = glyph.scale().y / SDF_GLYPH_SIZE;
+        let origin = glyph.position();
+        let screen_rect = Rect {
+            min: point(
+                origin.x + sdf_glyph.bounds.min.x * ratio,
+                origin.y + sdf_glyph.bounds.min.y * ratio,
+            ),
+            max: point(
+                origin.x + sdf_glyph.bounds.max.x * ratio,
+                origin.y + sdf_glyph.bounds.max.y * ratio,
+            ),
+        };
+        Some((sdf_glyph.uv_rect, screen_rect))
+    }
+
+    /// Replace the atlas with an empty one of a new size.
+    fn resize(&mut self, facade: &impl Facade, size: u32) -> Result<(), RenderError> {
+        self.texture = new_atlas_texture(facade, size)?;
+        self.size = size;
+        self.glyphs.clear();
+        self.cursor_x = 0;
+        self.cursor_y = 0;
+        self.row_height = 0;
+        Ok(())
+    }
+
+    fn add_glyphs<'g>(
+        &mut self,
+        glyphs: impl Iterator<Item = (usize, &'g PositionedGlyph<'g>)>,
+    ) -> Result<(), AtlasFull> {
+        for (font_id, glyph) in glyphs {
+            let key = (font_id, glyph.id());
+            if !self.glyphs.contains_key(&key) {
+                if let Some(sdf_glyph) = self.add_glyph(glyph.font(), glyph.id())? {
+                    self.glyphs.insert(key, sdf_glyph);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Make a glyph's distance field and write it into the atlas. Glyphs with nothing to draw
+    /// give None.
+    fn add_glyph(&mut self, font: &Font, glyph_id: GlyphId) -> Result<Option<SdfGlyph>, AtlasFull> {
+        let glyph = font
+            .glyph(glyph_id)
+            .scaled(Scale::uniform(SDF_GLYPH_SIZE))
+            .positioned(point(0.0, 0.0));
+        let bb = match glyph.pixel_bounding_box() {
+            Some(bb) => bb,
+            None => return Ok(None),
+        };
+
+        // Pad the glyph's bitmap so the distance field has room outside the glyph's edge.
+        let padding = SDF_SPREAD.ceil() as i32;
+        let width = (bb.width() + padding * 2) as usize;
+        let height = (bb.height() + padding * 2) as usize;
+        let (x, y) = self.allocate(width as u32, height as u32)?;
+
+        let mut coverage = vec![0.0; width * height];
+        glyph.draw(|x, y, v| {
+            let index = (y as usize + padding as usize) * width + x as usize + padding as usize;
+            coverage[index] = v;
+        });
+        let field = distance_field(&coverage, width, height, SDF_SPREAD);
+        self.texture.main_level().write(
+            glium::Rect {
+                left: x,
+                bottom: y,
+                width: width as u32,
+                height: height as u32,
+            },
+            glium::texture::RawImage2d {
+                data: Cow::Owned(field),
+                width: width as u32,
+                height: height as u32,
+                format: glium::texture::ClientFormat::U8,
+            },
+        );
+
+        let atlas_size = self.size as f32;
+        Ok(Some(SdfGlyph {
+            uv_rect: Rect {
+                min: point(x as f32 / atlas_size, y as f32 / atlas_size),
+                max: point(
+                    (x as usize + width) as f32 / atlas_size,
+                    (y as usize + height) as f32 / atlas_size,
+                ),
+            },
+            bounds: Rect {
+                min: point((bb.min.x - padding) as f32, (bb.min.y - padding) as f32),
+                max: point((bb.max.x + padding) as f32, (bb.max.y + padding) as f32),
+            },
+        }))
+    }
+
+    /// Find room in the atlas for a glyph.
+    fn allocate(&mut self, width: u32, height: u32) -> Result<(u32, u32), AtlasFull> {
+        if width > self.size || height > self.size {
+            return Err(AtlasFull);
+        }
+        // Start a new row when the glyph doesn't fit at the end of this one.
+        if self.cursor_x + width > self.size {
+            self.cursor_x = 0;
+            self.cursor_y += self.row_height;
+            self.row_height = 0;
+        }
+        if self.cursor_y + height > self.size {
+            return Err(AtlasFull);
+        }
+        let position = (self.cursor_x, self.cursor_y);
+        self.cursor_x += width;
+        self.row_height = self.row_height.max(height);
+        Ok(position)
+    }
+}
+
+pub fn sdf_shader_program(
+    facade: &impl Facade,
+    shader_version: ShaderVersion,
+) -> Result<Program, RenderError> {
+    shader_version.program(
+        facade,
+        "
+        IN vec2 position;
+        IN vec2 tex_coords;
+        IN vec4 colour;
+        IN vec4 outline_colour;
+        IN float outline_width;
+
+        OUT vec2 v_tex_coords;
+        OUT vec4 v_colour;
+        OUT vec4 v_outline_colour;
+        OUT float v_outline_width;
+
+        void main() {
+            gl_Position = vec4(position, 0.0, 1.0);
+            v_tex_coords = tex_coords;
+            v_colour = colour;
+            v_outline_colour = outline_colour;
+            v_outline_width = outline_width;
+        }
+    ",
+        "
+        uniform sampler2D tex;
+        IN vec2 v_tex_coords;
+        IN vec4 v_colour;
+        IN vec4 v_outline_colour;
+        IN float v_outline_width;
+
+        void main() {
+            // 0.5 is the glyph's edge. The outline covers the distance just outside it.
+            float field = TEXTURE(tex, v_tex_coords).r;
+        #ifdef HAS_DERIVATIVES
+            float smoothing = fwidth(field);
+        #else
+            // About a pixel at the size glyphs are rasterized at.
+            float smoothing = 0.06;
+        #endif
+            float fill = smoothstep(0.5 - smoothing, 0.5 + smoothing, field);
+            float outline_edge = 0.5 - v_outline_width;
+            float coverage = smoothstep(outline_edge - smoothing, outline_edge + smoothing, field);
+            vec4 colour = mix(v_outline_colour, v_colour, fill);
+            FRAG_COLOUR = vec4(colour.rgb, colour.a * coverage);
+        }
+    ",
+    )
+}
+
+#[derive(Copy, Clone)]
+pub struct SdfVertex {
+    pub position: [f32; 2],
+    pub tex_coords: [f32; 2],
+    pub colour: [f32; 4],
+    pub outline_colour: [f32; 4],
+    /// How far out the outline reaches, in the distance field's 0 to 1 units.
+    pub outline_width: f32,
+}
+implement_vertex!(
+    SdfVertex,
+    position,
+    tex_coords,
+    colour,
+    outline_colour,
+    outline_width
+);
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::ecs::system::render::headless::headless_context;
+
+    const ROBOTO: &[u8] = include_bytes!("../../../Roboto-Regular.ttf");
+
+    #[test]
+    fn a_full_atlas_grows_and_keeps_every_glyph_of_the_frame() {
+        let context = match headless_context() {
+            Ok(context) => context,
+            Err(err) => {
+                eprintln!("skipping, {}", err);
+                return;
+            }
+        };
+        let font = Font::try_from_bytes(ROBOTO).unwrap();
+        let glyphs: Vec<PositionedGlyph> = font
+            .layout(
+                "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+                Scale::uniform(32.0),
+                point(0.0, 32.0),
+            )
+            .collect();
+        let mut cache = SdfGlyphCache::new(&context).unwrap();
+        cache.resize(&context, 128).unwrap();
+        cache
+            .cache_glyphs(&context, glyphs.iter().map(|glyph| (0, glyph)))
+            .unwrap();
+        assert!(cache.size() > 128);
+
+        // Every glyph has its own part of the atlas, none of it reused by a later glyph.
+        let uv_rects: Vec<Rect<f32>> = glyphs
+            .iter()
+            .map(|glyph| cache.rect_for(0, glyph).unwrap().0)
+            .collect();
+        for (i, a) in uv_rects.iter().enumerate() {
+            for b in &uv_rects[i + 1..] {
+                let overlaps = a.min.x < b.max.x
+                    && b.min.x < a.max.x
+                    && a.min.y < b.max.y
+                    && b.min.y < a.max.y;
+                assert!(!overlaps, "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+}
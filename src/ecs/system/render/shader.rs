@@ -0,0 +1,128 @@
+use glium::backend::Facade;
+use glium::{Api, Program, Version};
+use std::fmt;
+
+use super::RenderError;
+
+/// A glsl version shaders can be compiled as. Shaders are written once with IN and OUT for
+/// their inputs and outputs, TEXTURE to sample textures, and FRAG_COLOUR for the fragment's
+/// colour, and each version's prelude defines those with its own syntax.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ShaderVersion {
+    /// Opengl es 2 and webgl.
+    Glsl100Es,
+    /// Opengl 2.0, for old drivers.
+    Glsl110,
+    /// Opengl 3.1.
+    Glsl140,
+    /// Opengl 3.3 and newer.
+    Glsl330,
+    /// Opengl es 3 and webgl 2.
+    Glsl300Es,
+}
+
+impl fmt::Display for ShaderVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderVersion::Glsl100Es => write!(f, "glsl 100 es"),
+            ShaderVersion::Glsl110 => write!(f, "glsl 110"),
+            ShaderVersion::Glsl140 => write!(f, "glsl 140"),
+            ShaderVersion::Glsl330 => write!(f, "glsl 330"),
+            ShaderVersion::Glsl300Es => write!(f, "glsl 300 es"),
+        }
+    }
+}
+
+impl ShaderVersion {
+    /// The newest version the context supports.
+    pub fn for_context(facade: &impl Facade) -> Result<Self, RenderError> {
+        let context = facade.get_context();
+        let supports =
+            |api, major, minor| context.is_glsl_version_supported(&Version(api, major, minor));
+        // Glium gives glsl versions before 330 as 1.1 for 110, up to 1.5 for 150.
+        let candidates = [
+            (ShaderVersion::Glsl330, supports(Api::Gl, 3, 3)),
+            (ShaderVersion::Glsl140, supports(Api::Gl, 1, 4)),
+            (ShaderVersion::Glsl110, supports(Api::Gl, 1, 1)),
+            (ShaderVersion::Glsl300Es, supports(Api::GlEs, 3, 0)),
+            (ShaderVersion::Glsl100Es, supports(Api::GlEs, 1, 0)),
+        ];
+        candidates
+            .into_iter()
+            .find(|(_, supported)| *supported)
+            .map(|(version, _)| version)
+            .ok_or_else(|| RenderError::UnsupportedGlsl(context.get_supported_glsl_version()))
+    }
+
+    fn is_es(&self) -> bool {
+        matches!(self, ShaderVersion::Glsl100Es | ShaderVersion::Glsl300Es)
+    }
+
+    /// Versions before in and out replaced attribute and varying.
+    fn is_legacy(&self) -> bool {
+        matches!(self, ShaderVersion::Glsl100Es | ShaderVersion::Glsl110)
+    }
+
+    fn version_directive(&self) -> &'static str {
+        match self {
+            ShaderVersion::Glsl100Es => "#version 100\n",
+            ShaderVersion::Glsl110 => "#version 110\n",
+            ShaderVersion::Glsl140 => "#version 140\n",
+            ShaderVersion::Glsl330 => "#version 330\n",
+            ShaderVersion::Glsl300Es => "#version 300 es\n",
+        }
+    }
+
+    fn vertex_prelude(&self) -> String {
+        let mut prelude = self.version_directive().to_string();
+        if self.is_legacy() {
+            prelude.push_str("#define IN attribute\n#define OUT varying\n");
+        } else {
+            prelude.push_str("#define IN in\n#define OUT out\n");
+        }
+        prelude
+    }
+
+    fn fragment_prelude(&self) -> String {
+        let mut prelude = self.version_directive().to_string();
+        // Glsl 100 only has fwidth with an extension. Shaders check HAS_DERIVATIVES before
+        // using it.
+        if *self == ShaderVersion::Glsl100Es {
+            prelude.push_str(
+                "#extension GL_OES_standard_derivatives : enable\n\
+                 #ifdef GL_OES_standard_derivatives\n#define HAS_DERIVATIVES\n#endif\n",
+            );
+        } else {
+            prelude.push_str("#define HAS_DERIVATIVES\n");
+        }
+        if self.is_es() {
+            prelude.push_str(
+                "#ifdef GL_FRAGMENT_PRECISION_HIGH\nprecision highp float;\n\
+                 #else\nprecision mediump float;\n#endif\n",
+            );
+        }
+        if self.is_legacy() {
+            prelude.push_str(
+                "#define IN varying\n#define TEXTURE texture2D\n#define FRAG_COLOUR gl_FragColor\n",
+            );
+        } else {
+            prelude.push_str(
+                "#define IN in\n#define TEXTURE texture\nout vec4 f_colour;\n#define FRAG_COLOUR f_colour\n",
+            );
+        }
+        prelude
+    }
+
+    /// Compile and link a vertex and fragment shader written without a #version, as this
+    /// version.
+    pub fn program(
+        &self,
+        facade: &impl Facade,
+        vertex: &str,
+        fragment: &str,
+    ) -> Result<Program, RenderError> {
+        let vertex = self.vertex_prelude() + vertex;
+        let fragment = self.fragment_prelude() + fragment;
+        Ok(Program::from_source(facade, &vertex, &fragment, None)?)
+    }
+}
@@ -0,0 +1,85 @@
+use rusttype::{point, Rect};
+
+use super::render::{Quad, RenderError, Renderer};
+use crate::ecs::component::sprite::{Shape, SpriteComponent};
+use crate::ecs::component::transform::{TransformComponent, Viewport};
+use crate::ecs::World;
+
+/// Draws sprite components as quads filling their transforms.
+#[derive(Default)]
+pub struct SpriteSystem {}
+
+impl SpriteSystem {
+    pub fn new() -> Self {
+        SpriteSystem {}
+    }
+
+    /// Draw every entity that has both a sprite and transform component, in entity order.
+    pub fn draw_world(
+        &mut self,
+        renderer: &mut dyn Renderer,
+        world: &World,
+    ) -> Result<(), RenderError> {
+        let sprites = match world.borrow_component_vec::<SpriteComponent>() {
+            Some(sprites) => sprites,
+            None => return Ok(()),
+        };
+        let transforms = match world.borrow_component_vec::<TransformComponent>() {
+            Some(transforms) => transforms,
+            None => return Ok(()),
+        };
+        let zip = sprites.iter().zip(transforms.iter());
+        self.draw_batch(
+            renderer,
+            zip.filter_map(|(sprite, transform)| Some((sprite.as_ref()?, transform.as_ref()?))),
+        )
+    }
+
+    /// Draw sprites in order, so later sprites are drawn over earlier ones.
+    pub fn draw_batch<'s>(
+        &mut self,
+        renderer: &mut dyn Renderer,
+        sprites: impl Iterator<Item = (&'s SpriteComponent, &'s TransformComponent)>,
+    ) -> Result<(), RenderError> {
+        let viewport = renderer.viewport();
+        let quads: Vec<Quad> = sprites
+            .map(|(sprite, transform)| sprite_quad(sprite, transform, &viewport))
+            .collect();
+        renderer.draw_quads(&quads)
+    }
+}
+
+/// The quad covering a sprite's shape, in physical pixels.
+fn sprite_quad(
+    sprite: &SpriteComponent,
+    transform: &TransformComponent,
+    viewport: &Viewport,
+) -> Quad {
+    let top_left = viewport.to_physical(transform.absolute_position(viewport));
+    let (width, height) = (
+        transform.width * viewport.scale_factor,
+        transform.height * viewport.scale_factor,
+    );
+    let mut rect = Rect {
+        min: point(top_left.x, top_left.y),
+        max: point(top_left.x + width, top_left.y + height),
+    };
+    let radius = match sprite.shape {
+        Shape::Rectangle => 0.0,
+        Shape::RoundedRectangle { radius } => radius * viewport.scale_factor,
+        Shape::Circle => {
+            // Shrink the rect to a square in the middle of the transform.
+            let size = width.min(height);
+            rect.min.x += (width - size) / 2.0;
+            rect.min.y += (height - size) / 2.0;
+            rect.max = point(rect.min.x + size, rect.min.y + size);
+            size / 2.0
+        }
+    };
+    Quad {
+        rect,
+        radius,
+        colour: sprite.colour,
+        texture: sprite.texture,
+    }
+}
@@ -1,310 +1,240 @@
-use glium::{implement_vertex, program, uniform, Display, Frame, Program, Surface, Texture2d};
-use rusttype::{gpu_cache::Cache, point, vector, Font, PositionedGlyph, Rect, Scale};
-use std::borrow::Cow;
+pub mod font;
+pub mod layout;
+pub mod shaping;
 
-use crate::ecs::component::text::{TextAlignment, TextComponent};
+use rusttype::{point, vector, Rect, Scale};
+
+use self::font::{FontError, FontHandle, FontRegistry};
+use self::layout::{LayoutCache, LayoutStats, TextMetrics, FONT_SIZE};
+use self::shaping::Shaper;
+use super::render::{RenderError, Renderer, TextRun};
+use crate::ecs::component::text::TextComponent;
 use crate::ecs::component::transform::{Position, TransformComponent};
+use crate::ecs::World;
+
+/// Colours of the debug outlines around text.
+pub const TRANSFORM_OUTLINE_COLOUR: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+pub const BOUNDS_OUTLINE_COLOUR: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+pub const LINE_OUTLINE_COLOUR: [f32; 4] = [0.0, 0.6, 0.0, 1.0];
+pub const GLYPH_OUTLINE_COLOUR: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
 
+/// Lays out text components and hands their glyphs to a Renderer.
 pub struct TextSystem {
-    font: Font<'static>,
-    glyph_cache: Cache<'static>,
-    glyph_cache_texture: Texture2d,
-    shader_program: Program,
+    fonts: FontRegistry,
+    /// Fonts tried in order for characters a text's own font doesn't have.
+    fallback_fonts: Vec<FontHandle>,
+    layout_cache: LayoutCache,
+    debug_outlines: bool,
 }
 
 impl TextSystem {
-    pub fn new(display: &Display) -> Self {
-        // Initialize font.
-        let font_data = include_bytes!("../../Roboto-Regular.ttf");
-        let font = Font::try_from_bytes(font_data).unwrap();
+    pub fn new() -> Result<Self, FontError> {
+        // Initialize fonts. Roboto is loaded first so it gets the default font handle.
+        let mut fonts = FontRegistry::new();
+        fonts.load_static(include_bytes!("../../Roboto-Regular.ttf"))?;
+
+        Ok(TextSystem {
+            fonts,
+            fallback_fonts: Vec::new(),
+            layout_cache: LayoutCache::new(),
+            debug_outlines: false,
+        })
+    }
 
-        // Initialize gpu cache
-        let scale = display.gl_window().window().scale_factor();
-        let (cache_width, cache_height) = ((512.0 * scale) as u32, (512.0 * scale) as u32);
-        let glyph_cache: Cache<'static> = Cache::builder()
-            .dimensions(cache_width, cache_height)
-            .build();
+    /// Measure text as if it was laid out from the origin at the given width, in logical pixels.
+    pub fn measure(&self, text: &TextComponent, width: f32) -> Result<TextMetrics, FontError> {
+        let font_chain = self.font_chain(text.font);
+        let shaper = Shaper::new(&self.fonts, &font_chain, Scale::uniform(FONT_SIZE))?;
+        let origin = Position { x: 0.0, y: 0.0 };
+        Ok(layout::layout_paragraph(&shaper, width, None, text, origin).metrics)
+    }
 
-        // Initialize shader program.
-        let shader_program = program!(
-            display,
-            140 => {
-                vertex: "
-                #version 140
+    /// When on, outlines are drawn around each text's transform, its lines, and its glyphs.
+    pub fn set_debug_outlines(&mut self, debug_outlines: bool) {
+        self.debug_outlines = debug_outlines;
+    }
 
-                in vec2 position;
-                in vec2 tex_coords;
-                in vec4 colour;
+    pub fn fonts(&self) -> &FontRegistry {
+        &self.fonts
+    }
 
-                out vec2 v_tex_coords;
-                out vec4 v_colour;
+    /// Load more fonts here, then reference them from a TextComponent with the returned handle.
+    pub fn fonts_mut(&mut self) -> &mut FontRegistry {
+        &mut self.fonts
+    }
 
-                void main() {
-                    gl_Position = vec4(position, 0.0, 1);
-                    v_tex_coords = tex_coords;
-                    v_colour = colour;
-                }
-            ",
-                fragment: "
-                #version 140
-                uniform sampler2D tex;
-                in vec2 v_tex_coords;
-                in vec4 v_colour;
-                out vec4 f_colour;
+    /// Set the fonts characters fall back to when a text's font doesn't have them, like fonts
+    /// for CJK or emoji. Each character comes from the first font in the list that has it.
+    pub fn set_fallback_fonts(&mut self, fallback_fonts: Vec<FontHandle>) {
+        self.fallback_fonts = fallback_fonts;
+        // Cached layouts may have glyphs from the old fallbacks.
+        self.layout_cache.clear();
+    }
 
-                void main() {
-                    f_colour = v_colour * vec4(1.0, 1.0, 1.0, texture(tex, v_tex_coords).r);
-                }
-            "
-            }
-        )
-        .unwrap();
+    /// The fonts a text's glyphs can come from, starting with its own font. Text referencing a
+    /// font that was never loaded uses the default font instead.
+    fn font_chain(&self, font: FontHandle) -> Vec<FontHandle> {
+        let font = match self.fonts.get(font) {
+            Some(_) => font,
+            None => FontHandle::default(),
+        };
+        let mut font_chain = vec![font];
+        font_chain.extend(self.fallback_fonts.iter().filter(|&&handle| handle != font));
+        font_chain
+    }
 
-        // Init gpu cache texture.
-        let glyph_cache_texture = Texture2d::with_format(
-            display,
-            glium::texture::RawImage2d {
-                data: Cow::Owned(vec![128u8; cache_width as usize * cache_height as usize]),
-                width: cache_width,
-                height: cache_height,
-                format: glium::texture::ClientFormat::U8,
-            },
-            glium::texture::UncompressedFloatFormat::U8,
-            glium::texture::MipmapsOption::NoMipmap,
-        )
-        .unwrap();
+    /// How many texts were drawn from a cached layout instead of being laid out again.
+    pub fn layout_stats(&self) -> LayoutStats {
+        self.layout_cache.stats()
+    }
 
-        TextSystem {
-            font,
-            glyph_cache,
-            shader_program,
-            glyph_cache_texture,
-        }
+    /// Forget the layouts of texts that weren't drawn since the last end_frame, like ones
+    /// removed from the world. Call once a frame, after drawing.
+    pub fn end_frame(&mut self) {
+        self.layout_cache.end_frame();
     }
+
+    /// Draw every entity that has both a text and transform component.
+    pub fn draw_world(
+        &mut self,
+        renderer: &mut dyn Renderer,
+        world: &World,
+    ) -> Result<(), RenderError> {
+        let texts = match world.borrow_component_vec::<TextComponent>() {
+            Some(texts) => texts,
+            None => return Ok(()),
+        };
+        let transforms = match world.borrow_component_vec::<TransformComponent>() {
+            Some(transforms) => transforms,
+            None => return Ok(()),
+        };
+        let zip = texts.iter().zip(transforms.iter()).enumerate();
+        self.draw_batch(
+            renderer,
+            zip.filter_map(|(entity, (text, transform))| {
+                Some((entity, text.as_ref()?, transform.as_ref()?))
+            }),
+        )
+    }
+
     pub fn draw(
         &mut self,
-        frame: &mut Frame,
-        display: &Display,
+        renderer: &mut dyn Renderer,
+        entity: usize,
         text: &TextComponent,
         transform: &TransformComponent,
-    ) {
-        let scale = display.gl_window().window().scale_factor() as f32;
-        // let (width, _): (u32, _) = display.gl_window().window().inner_size().into();
+    ) -> Result<(), RenderError> {
+        self.draw_batch(renderer, std::iter::once((entity, text, transform)))
+    }
 
-        // Get glyphs and queue in cache
-        let paragraph_position = transform.absolute_position(display);
-        let glyphs = self.layout_paragraph(
-            &self.font,
-            Scale::uniform(24.0 * scale),
-            transform.width,
-            &text.text,
-            paragraph_position,
-            &text.alignment,
-        );
-        for glyph in &glyphs {
-            self.glyph_cache.queue_glyph(0, glyph.clone());
-        }
-        self.glyph_cache
-            .cache_queued(|rect, data| {
-                // Write a glyph's image to a part rect in the texture.
-                self.glyph_cache_texture.main_level().write(
-                    glium::Rect {
-                        left: rect.min.x,
-                        bottom: rect.min.y,
-                        width: rect.width(),
-                        height: rect.height(),
-                    },
-                    glium::texture::RawImage2d {
-                        data: Cow::Borrowed(data),
-                        width: rect.width(),
-                        height: rect.height(),
-                        format: glium::texture::ClientFormat::U8,
-                    },
+    /// Lay out all the texts, then hand them to the renderer together so it can draw them in
+    /// as few draw calls as it can.
+    pub fn draw_batch<'t>(
+        &mut self,
+        renderer: &mut dyn Renderer,
+        texts: impl Iterator<Item = (usize, &'t TextComponent, &'t TransformComponent)>,
+    ) -> Result<(), RenderError> {
+        let viewport = renderer.viewport();
+
+        // Get glyphs of every text, remembering which font each glyph is from.
+        let mut runs: Vec<TextRun> = Vec::new();
+        let mut outlines: Vec<(Rect<f32>, [f32; 4])> = Vec::new();
+        for (entity, text, transform) in texts {
+            let font_chain = self.font_chain(text.font);
+            let layout = self.layout_cache.layout(
+                entity,
+                &self.fonts,
+                &font_chain,
+                viewport.scale_factor,
+                text,
+                transform,
+            )?;
+            // Layouts are cached at the origin, so move the glyphs to where the text is.
+            let paragraph_position = viewport.to_physical(transform.absolute_position(&viewport));
+            let glyphs = layout.glyphs.iter().map(|glyph| {
+                let mut glyph = glyph.clone();
+                let mut new_pos = glyph.position();
+                new_pos.x += paragraph_position.x;
+                new_pos.y += paragraph_position.y;
+                glyph.set_position(new_pos);
+                glyph
+            });
+            runs.push(TextRun {
+                glyphs: glyphs.collect(),
+                fonts: layout.fonts.clone(),
+                colour: text.colour,
+                effects: text.effects,
+            });
+
+            if self.debug_outlines {
+                let offset = vector(paragraph_position.x, paragraph_position.y);
+                let moved = |rect: &Rect<f32>| Rect {
+                    min: rect.min + offset,
+                    max: rect.max + offset,
+                };
+                let transform_box = Rect {
+                    min: point(0.0, 0.0),
+                    max: point(
+                        transform.width * viewport.scale_factor,
+                        transform.height * viewport.scale_factor,
+                    ),
+                };
+                let metrics = &layout.metrics;
+                outlines.push((moved(&transform_box), TRANSFORM_OUTLINE_COLOUR));
+                outlines.extend(
+                    metrics
+                        .glyphs
+                        .iter()
+                        .map(|r| (moved(r), GLYPH_OUTLINE_COLOUR)),
                 );
-            })
-            .unwrap();
-
-        // Init texture uniform from cache texture.
-        let uniforms = uniform! {
-            tex: self.glyph_cache_texture.sampled().magnify_filter(glium::uniforms::MagnifySamplerFilter::Nearest)
-        };
-
-        // Init vertex buffer for paragraph rect vertices including position, texture coordinates,
-        // and colour.
-        let vertex_buffer = {
-            #[derive(Copy, Clone)]
-            struct Vertex {
-                position: [f32; 2],
-                tex_coords: [f32; 2],
-                colour: [f32; 4],
+                outlines.extend(
+                    metrics
+                        .lines
+                        .iter()
+                        .map(|r| (moved(r), LINE_OUTLINE_COLOUR)),
+                );
+                outlines.push((moved(&metrics.bounds), BOUNDS_OUTLINE_COLOUR));
             }
-            implement_vertex!(Vertex, position, tex_coords, colour);
-            let colour = [0.0, 0.0, 0.0, 1.0];
-            let (screen_width, screen_height) = {
-                let (w, h) = display.get_framebuffer_dimensions();
-                (w as f32, h as f32)
-            };
-            let origin = point(0.0, 0.0);
-            let vertices: Vec<Vertex> = glyphs
-                .iter()
-                // Get the rect for a glyph
-                .filter_map(|g| self.glyph_cache.rect_for(0, g).ok().flatten())
-                // uv_rect is where the glyph is in the cache texture
-                // screen_rect is where the glyph is going to be drawn on the screen in pixel space
-                .flat_map(|(uv_rect, screen_rect)| {
-                    // This converts the pixel-space coordinate system of the glyphs (where the
-                    // top left is (0,0) and bottom right is (512,512)) to opengl's coordinate
-                    // system, where the top left is (-1, 1) and the bottom right is (1, -1).
-                    let gl_rect = Rect {
-                        min: origin
-                            + (vector(
-                                screen_rect.min.x as f32 / screen_width - 0.5,
-                                1.0 - screen_rect.min.y as f32 / screen_height - 0.5,
-                            )) * 2.0,
-                        max: origin
-                            + (vector(
-                                screen_rect.max.x as f32 / screen_width - 0.5,
-                                1.0 - screen_rect.max.y as f32 / screen_height - 0.5,
-                            )) * 2.0,
-                    };
+        }
+        renderer.draw_outlines(&outlines)?;
+        renderer.draw_text(&runs)
+    }
+}
 
-                    // Each set of 6 vertices represents two triangles forming a rectangle
-                    // around one glyph.
-                    vec![
-                        Vertex {
-                            position: [gl_rect.min.x, gl_rect.max.y],
-                            tex_coords: [uv_rect.min.x, uv_rect.max.y],
-                            colour,
-                        },
-                        Vertex {
-                            position: [gl_rect.min.x, gl_rect.min.y],
-                            tex_coords: [uv_rect.min.x, uv_rect.min.y],
-                            colour,
-                        },
-                        Vertex {
-                            position: [gl_rect.max.x, gl_rect.min.y],
-                            tex_coords: [uv_rect.max.x, uv_rect.min.y],
-                            colour,
-                        },
-                        Vertex {
-                            position: [gl_rect.max.x, gl_rect.min.y],
-                            tex_coords: [uv_rect.max.x, uv_rect.min.y],
-                            colour,
-                        },
-                        Vertex {
-                            position: [gl_rect.max.x, gl_rect.max.y],
-                            tex_coords: [uv_rect.max.x, uv_rect.max.y],
-                            colour,
-                        },
-                        Vertex {
-                            position: [gl_rect.min.x, gl_rect.max.y],
-                            tex_coords: [uv_rect.min.x, uv_rect.max.y],
-                            colour,
-                        },
-                    ]
-                })
-                .collect();
 
-            glium::VertexBuffer::new(display, &vertices).unwrap()
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::transform::Viewport;
+    use crate::ecs::system::render::recording::RecordingRenderer;
+
+    /// Has Hebrew, Arabic and symbols like ✓, which Roboto doesn't.
+    const DEJAVU_SANS: &[u8] = include_bytes!("../../../tests/fonts/DejaVuSans.ttf");
+
+    #[test]
+    fn characters_the_font_doesnt_have_come_from_a_fallback_font() {
+        let mut text_system = TextSystem::new().unwrap();
+        let dejavu = text_system.fonts_mut().load_static(DEJAVU_SANS).unwrap();
+        text_system.set_fallback_fonts(vec![dejavu]);
+        let roboto = FontHandle::default();
+        assert_eq!(text_system.fonts().get(roboto).unwrap().glyph('✓').id().0, 0);
+
+        let mut renderer = RecordingRenderer::new(Viewport {
+            width: 512.0,
+            height: 512.0,
+            scale_factor: 1.0,
+        });
+        let text = TextComponent {
+            text: "a✓b".to_string(),
+            ..Default::default()
         };
-
-        // Draw the text's vertices with uniforms and shader program
-        frame
-            .draw(
-                &vertex_buffer,
-                glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
-                &self.shader_program,
-                &uniforms,
-                &glium::DrawParameters {
-                    blend: glium::Blend::alpha_blending(),
-                    ..Default::default()
-                },
-            )
+        text_system
+            .draw(&mut renderer, 0, &text, &TransformComponent::new())
             .unwrap();
-    }
 
-    fn layout_paragraph<'a>(
-        &self,
-        font: &Font<'a>,
-        scale: Scale,
-        max_paragraph_width: f32,
-        text: &str,
-        position: Position,
-        text_alignment: &TextAlignment,
-    ) -> Vec<PositionedGlyph<'a>> {
-        // Init some font stats.
-        let v_metrics = font.v_metrics(scale);
-        let advance_height = v_metrics.ascent - v_metrics.descent + v_metrics.line_gap;
-        // This is the insertion point we use to position characters.
-        let mut caret = point(position.x, position.y + v_metrics.ascent);
-        let mut last_glyph_id = None;
-        let mut glyph_lines: Vec<Vec<PositionedGlyph>> = vec![vec![]];
-        let mut line_index = 0;
-        for c in text.chars() {
-            // Handle control characters
-            if c.is_control() {
-                match c {
-                    '\r' => {
-                        // If a newline is entered, reset the insertion point the start and one linedown.
-                        caret = point(position.x, caret.y + advance_height);
-                        glyph_lines.push(Vec::new());
-                        line_index += 1;
-                    }
-                    '\n' => {}
-                    _ => {}
-                }
-                continue;
-            }
-
-            let base_glyph = font.glyph(c);
-
-            // If there's a previous glyph, add any kerning (additional h spacing) necessary between the
-            // previous and next glyph.
-            if let Some(id) = last_glyph_id.take() {
-                caret.x += font.pair_kerning(scale, id, base_glyph.id());
-            }
-            last_glyph_id = Some(base_glyph.id());
-
-            // Position the glyph.
-            let mut glyph = base_glyph.scaled(scale).positioned(caret);
-            // If we accidentally positioned it past the maximum width of the paragaph, move the
-            // insertion caret to the next line and put the glyph there instead.
-            if let Some(bb) = glyph.pixel_bounding_box() {
-                if bb.max.x > position.x as i32 + max_paragraph_width as i32 {
-                    caret = point(position.x, caret.y + advance_height);
-                    glyph.set_position(caret);
-                    last_glyph_id = None;
-                    glyph_lines.push(Vec::new());
-                    line_index += 1;
-                }
-            }
-            // Position the caret ahead where the next glyph would go.
-            caret.x += glyph.unpositioned().h_metrics().advance_width;
-
-            glyph_lines[line_index].push(glyph);
-        }
-
-        // Align text
-        match text_alignment {
-            TextAlignment::Center => {
-                for glyphs in &mut glyph_lines {
-                    let line_width = glyphs.iter().fold(0.0, |acc, g| {
-                        if let Some(bb) = g.pixel_bounding_box() {
-                            acc + bb.width() as f32
-                        } else {
-                            acc
-                        }
-                    });
-                    let unused_paragraph_width = max_paragraph_width - line_width;
-                    for g in glyphs {
-                        let mut new_pos = g.position();
-                        new_pos.x += unused_paragraph_width / 2.0;
-                        g.set_position(new_pos);
-                    }
-                }
-            }
-            _ => {}
-        }
-        glyph_lines.iter().flatten().cloned().collect()
+        let run = renderer.text_runs().next().unwrap();
+        assert_eq!(run.fonts, [roboto, dejavu, roboto]);
+        let check = text_system.fonts().get(dejavu).unwrap().glyph('✓').id();
+        assert_eq!(run.glyphs[1].id(), check);
     }
 }
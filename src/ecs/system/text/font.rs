@@ -0,0 +1,219 @@
+use rusttype::Font;
+use std::fmt;
+use std::path::Path;
+
+/// Refers to a font loaded into a FontRegistry. The handle's index is also the font id used in
+/// the glyph cache.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FontHandle(usize);
+
+impl FontHandle {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug)]
+pub enum FontError {
+    /// The font file couldn't be read from disk.
+    Io(std::io::Error),
+    /// The bytes weren't a TTF or OTF font rusttype could parse.
+    InvalidFontData,
+    /// No fonts are loaded, so there's nothing to draw text with.
+    NoFonts,
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontError::Io(err) => write!(f, "could not read font file: {}", err),
+            FontError::InvalidFontData => write!(f, "font data is not a valid TTF or OTF font"),
+            FontError::NoFonts => write!(f, "no fonts are loaded to draw text with"),
+        }
+    }
+}
+
+impl std::error::Error for FontError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FontError::Io(err) => Some(err),
+            FontError::InvalidFontData | FontError::NoFonts => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for FontError {
+    fn from(err: std::io::Error) -> Self {
+        FontError::Io(err)
+    }
+}
+
+/// A font and the bytes it was loaded from. Shaping reads tables rusttype doesn't expose, so
+/// it needs the bytes.
+pub struct LoadedFont {
+    pub font: Font<'static>,
+    pub data: &'static [u8],
+}
+
+/// The id of the family name, like "Roboto", in a font's name table.
+const FAMILY_NAME_ID: u16 = 1;
+
+impl LoadedFont {
+    /// The font's family name from its name table. Unicode and Windows names are preferred, Mac
+    /// ones are only read as ASCII.
+    pub fn family_name(&self) -> Option<String> {
+        let data = self.data;
+        let read_u16 = |offset: usize| {
+            let bytes = data.get(offset..offset + 2)?;
+            Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+        };
+        // The table directory comes after a 12 byte header, with 16 bytes for each table.
+        let num_tables = read_u16(4)? as usize;
+        let name_record = (0..num_tables)
+            .map(|i| 12 + i * 16)
+            .find(|&record| data.get(record..record + 4) == Some(b"name"))?;
+        let table =
+            (read_u16(name_record + 8)? as usize) << 16 | read_u16(name_record + 10)? as usize;
+        let count = read_u16(table + 2)? as usize;
+        let strings = table + read_u16(table + 4)? as usize;
+        let mut mac_name = None;
+        for record in (0..count).map(|i| table + 6 + i * 12) {
+            if read_u16(record + 6)? != FAMILY_NAME_ID {
+                continue;
+            }
+            let length = read_u16(record + 8)? as usize;
+            let start = strings + read_u16(record + 10)? as usize;
+            let bytes = data.get(start..start + length)?;
+            match read_u16(record)? {
+                // Unicode and Windows names are UTF-16.
+                0 | 3 => {
+                    let units: Vec<u16> = bytes
+                        .chunks_exact(2)
+                        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                        .collect();
+                    return String::from_utf16(&units).ok();
+                }
+                1 => mac_name = Some(bytes.iter().map(|&byte| byte as char).collect()),
+                _ => {}
+            }
+        }
+        mac_name
+    }
+}
+
+/// Owns every font text can be drawn with. Text components reference fonts by the FontHandle
+/// returned when the font was loaded. The first font loaded gets the default handle.
+#[derive(Default)]
+pub struct FontRegistry {
+    fonts: Vec<LoadedFont>,
+}
+
+impl FontRegistry {
+    pub fn new() -> Self {
+        FontRegistry { fonts: Vec::new() }
+    }
+
+    /// Load a font embedded in the binary, like with include_bytes!.
+    pub fn load_static(&mut self, data: &'static [u8]) -> Result<FontHandle, FontError> {
+        let font = Font::try_from_bytes(data).ok_or(FontError::InvalidFontData)?;
+        Ok(self.insert(LoadedFont { font, data }))
+    }
+
+    /// Load a font from bytes, like a font file read at runtime. Glyphs keep the font they're
+    /// from and can outlive the registry, so the bytes are never freed. Then the font and its
+    /// shaping tables can both borrow one copy of them.
+    pub fn load_bytes(&mut self, data: Vec<u8>) -> Result<FontHandle, FontError> {
+        // Check it's a font first so bad data isn't kept forever.
+        if Font::try_from_bytes(&data).is_none() {
+            return Err(FontError::InvalidFontData);
+        }
+        self.load_static(Box::leak(data.into_boxed_slice()))
+    }
+
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> Result<FontHandle, FontError> {
+        let data = std::fs::read(path)?;
+        self.load_bytes(data)
+    }
+
+    pub fn get(&self, handle: FontHandle) -> Option<&Font<'static>> {
+        self.fonts.get(handle.0).map(|loaded| &loaded.font)
+    }
+
+    pub fn get_loaded(&self, handle: FontHandle) -> Option<&LoadedFont> {
+        self.fonts.get(handle.0)
+    }
+
+    /// The first font loaded from a family, like "Roboto". Case doesn't matter.
+    pub fn find_family(&self, family: &str) -> Option<FontHandle> {
+        self.fonts
+            .iter()
+            .position(|loaded| {
+                loaded
+                    .family_name()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(family))
+            })
+            .map(FontHandle)
+    }
+
+    pub fn len(&self) -> usize {
+        self.fonts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fonts.is_empty()
+    }
+
+    fn insert(&mut self, font: LoadedFont) -> FontHandle {
+        self.fonts.push(font);
+        FontHandle(self.fonts.len() - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTO: &[u8] = include_bytes!("../../../Roboto-Regular.ttf");
+    const DEJAVU_SANS: &[u8] = include_bytes!("../../../../tests/fonts/DejaVuSans.ttf");
+
+    #[test]
+    fn the_first_font_loaded_gets_the_default_handle() {
+        let mut fonts = FontRegistry::new();
+        assert!(fonts.get(FontHandle::default()).is_none());
+        assert_eq!(fonts.load_static(ROBOTO).unwrap(), FontHandle::default());
+        let dejavu = fonts.load_bytes(DEJAVU_SANS.to_vec()).unwrap();
+        assert_eq!(dejavu.index(), 1);
+        assert_eq!(fonts.len(), 2);
+        assert_eq!(fonts.get_loaded(dejavu).unwrap().data, DEJAVU_SANS);
+    }
+
+    #[test]
+    fn bytes_that_arent_a_font_are_invalid() {
+        let mut fonts = FontRegistry::new();
+        let result = fonts.load_static(b"not a font");
+        assert!(matches!(result, Err(FontError::InvalidFontData)));
+        let result = fonts.load_bytes(ROBOTO[..100].to_vec());
+        assert!(matches!(result, Err(FontError::InvalidFontData)));
+        assert!(fonts.is_empty());
+    }
+
+    #[test]
+    fn a_missing_font_file_cant_be_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = FontRegistry::new().load_file(dir.path().join("missing.ttf"));
+        assert!(matches!(result, Err(FontError::Io(_))));
+    }
+
+    #[test]
+    fn fonts_are_found_by_family_name() {
+        let mut fonts = FontRegistry::new();
+        let roboto = fonts.load_static(ROBOTO).unwrap();
+        let dejavu = fonts.load_bytes(DEJAVU_SANS.to_vec()).unwrap();
+        let family = |handle| fonts.get_loaded(handle).unwrap().family_name();
+        assert_eq!(family(roboto).as_deref(), Some("Roboto"));
+        assert_eq!(family(dejavu).as_deref(), Some("DejaVu Sans"));
+        assert_eq!(fonts.find_family("Roboto"), Some(roboto));
+        assert_eq!(fonts.find_family("dejavu sans"), Some(dejavu));
+        assert_eq!(fonts.find_family("Comic Sans"), None);
+    }
+}
@@ -0,0 +1,896 @@
+use rusttype::{point, Point, PositionedGlyph, Rect, Scale, VMetrics};
+use std::collections::HashMap;
+use unicode_linebreak::{linebreaks, BreakOpportunity};
+
+use super::font::{FontError, FontHandle, FontRegistry};
+use super::shaping::Shaper;
+use crate::ecs::component::text::{TextAlignment, TextComponent, TextOverflow, VerticalAlignment};
+use crate::ecs::component::transform::{Position, TransformComponent};
+
+/// Font size of all text, in logical pixels.
+pub const FONT_SIZE: f32 = 24.0;
+const ELLIPSIS: char = '…';
+
+/// The positioned glyphs of a text, and the boxes around them.
+pub struct TextLayout<'a> {
+    pub glyphs: Vec<PositionedGlyph<'a>>,
+    /// The font each glyph came from, which isn't the text's font when it fell back.
+    pub fonts: Vec<FontHandle>,
+    pub metrics: TextMetrics,
+}
+
+/// Boxes around laid out text, for hit testing and outlining. Rects are in the same pixels
+/// and from the same origin as the layout they measure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextMetrics {
+    /// One rect per line, from the start of the line to the end of its last visible glyph, and
+    /// from the line's ascent to its descent.
+    pub lines: Vec<Rect<f32>>,
+    /// The rect around every line.
+    pub bounds: Rect<f32>,
+    /// One rect per glyph, as wide as the glyph's advance and as tall as its line.
+    pub glyphs: Vec<Rect<f32>>,
+}
+
+/// Break a text component's text into lines that fit the paragraph width, then position and
+/// align each line's glyphs inside the paragraph's box. Without a paragraph height the text is
+/// laid out from the top, like when measuring text that isn't in a box.
+pub fn layout_paragraph(
+    shaper: &Shaper,
+    max_paragraph_width: f32,
+    paragraph_height: Option<f32>,
+    text: &TextComponent,
+    position: Position,
+) -> TextLayout<'static> {
+    // Init some font stats.
+    let v_metrics = shaper.font().v_metrics(shaper.scale());
+    let advance_height = v_metrics.ascent - v_metrics.descent + v_metrics.line_gap;
+
+    let mut text_lines = break_lines(shaper, &text.text, max_paragraph_width);
+    if let Some(max_lines) = text.max_lines {
+        if text_lines.len() > max_lines {
+            text_lines.truncate(max_lines);
+            if let (TextOverflow::Ellipsis, Some(last_line)) =
+                (&text.overflow, text_lines.last_mut())
+            {
+                add_ellipsis(shaper, last_line, max_paragraph_width);
+            }
+        }
+    }
+
+    // Position each line's glyphs one line height below the last.
+    let mut lines: Vec<LayoutLine> = text_lines
+        .into_iter()
+        .enumerate()
+        .map(|(line_index, text_line)| {
+            let baseline = point(
+                position.x,
+                position.y + v_metrics.ascent + line_index as f32 * advance_height,
+            );
+            LayoutLine::new(shaper, text_line, baseline)
+        })
+        .collect();
+
+    // Align text horizontally
+    let line_count = lines.len();
+    for (line_index, line) in lines.iter_mut().enumerate() {
+        let unused_line_width = max_paragraph_width - line.width;
+        match text.alignment {
+            TextAlignment::Left => {}
+            TextAlignment::Center => line.shift_x(unused_line_width / 2.0),
+            TextAlignment::Right => line.shift_x(unused_line_width),
+            TextAlignment::Justify => {
+                // The last line of a paragraph stays left aligned, like in a book.
+                let is_last_line = line_index == line_count - 1 || line.hard_break;
+                if !is_last_line {
+                    line.justify(unused_line_width);
+                }
+            }
+        }
+    }
+
+    // Align text vertically inside the paragraph's box. The line gap is only between
+    // lines, so the last line doesn't count it.
+    if let Some(paragraph_height) = paragraph_height {
+        let text_height = (line_count as f32 * advance_height - v_metrics.line_gap).max(0.0);
+        let unused_paragraph_height = paragraph_height - text_height;
+        let y_offset = match text.vertical_alignment {
+            VerticalAlignment::Top => 0.0,
+            VerticalAlignment::Middle => unused_paragraph_height / 2.0,
+            VerticalAlignment::Bottom => unused_paragraph_height,
+        };
+        for line in &mut lines {
+            line.shift_y(y_offset);
+        }
+    }
+
+    let metrics = measure_lines(&lines, v_metrics, point(position.x, position.y));
+    let mut layout = TextLayout {
+        glyphs: Vec::new(),
+        fonts: Vec::new(),
+        metrics,
+    };
+    for line in lines {
+        layout.glyphs.extend(line.glyphs);
+        layout.fonts.extend(line.fonts);
+    }
+    layout
+}
+
+/// Measure the lines of a paragraph. Without any lines, like when max_lines is 0, the bounds
+/// are an empty rect at the paragraph's origin.
+fn measure_lines(lines: &[LayoutLine], v_metrics: VMetrics, origin: Point<f32>) -> TextMetrics {
+    if lines.is_empty() {
+        return TextMetrics {
+            lines: Vec::new(),
+            bounds: Rect {
+                min: origin,
+                max: origin,
+            },
+            glyphs: Vec::new(),
+        };
+    }
+    let mut metrics = TextMetrics {
+        lines: Vec::with_capacity(lines.len()),
+        bounds: Rect {
+            min: point(f32::MAX, f32::MAX),
+            max: point(f32::MIN, f32::MIN),
+        },
+        glyphs: Vec::new(),
+    };
+    for line in lines {
+        // Descent is negative, it's how far below the baseline the line goes.
+        let top = line.origin.y - v_metrics.ascent;
+        let bottom = line.origin.y - v_metrics.descent;
+        let line_rect = Rect {
+            min: point(line.origin.x, top),
+            max: point(line.origin.x + line.width, bottom),
+        };
+        metrics.bounds.min.x = metrics.bounds.min.x.min(line_rect.min.x);
+        metrics.bounds.min.y = metrics.bounds.min.y.min(line_rect.min.y);
+        metrics.bounds.max.x = metrics.bounds.max.x.max(line_rect.max.x);
+        metrics.bounds.max.y = metrics.bounds.max.y.max(line_rect.max.y);
+        metrics.lines.push(line_rect);
+        for glyph in &line.glyphs {
+            let x = glyph.position().x;
+            let advance_width = glyph.unpositioned().h_metrics().advance_width;
+            metrics.glyphs.push(Rect {
+                min: point(x, top),
+                max: point(x + advance_width, bottom),
+            });
+        }
+    }
+    metrics
+}
+
+/// The characters of one line of text, before any glyphs are positioned.
+struct TextLine {
+    chars: Vec<char>,
+    /// True when the line was ended by a line break in the text, rather than by wrapping.
+    hard_break: bool,
+}
+
+impl TextLine {
+    /// A line ended by wrapping. Whitespace at a wrap point would only push the line off
+    /// alignment, so it's dropped.
+    fn wrapped(mut chars: Vec<char>) -> Self {
+        chars.truncate(trim_end(&chars).len());
+        TextLine {
+            chars,
+            hard_break: false,
+        }
+    }
+}
+
+/// Split text into lines no wider than max_width. Lines wrap at unicode line break
+/// opportunities, like between words, and words too long for a line on their own are broken
+/// wherever they reach the edge. "\n", "\r\n" and "\r" all start a new line, and blank lines
+/// are kept as empty lines.
+///
+/// Each segment between break opportunities is shaped once, and lines are measured by adding
+/// up its characters' advances, so long lines aren't shaped again for every word.
+fn break_lines(shaper: &Shaper, text: &str, max_width: f32) -> Vec<TextLine> {
+    let mut lines = Vec::new();
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    for paragraph in text.split('\n') {
+        // Tabs are kept so they can be moved to the next tab stop, other control characters
+        // aren't drawn.
+        let paragraph: String = paragraph
+            .chars()
+            .filter(|&c| c == '\t' || !c.is_control())
+            .collect();
+        let mut line: Vec<char> = Vec::new();
+        // Where the caret is after the line's characters, trailing whitespace and all.
+        let mut line_width = 0.0;
+        let mut segment_start = 0;
+        for (segment_end, opportunity) in linebreaks(&paragraph) {
+            // A segment is the text between two break opportunities, usually a word and the
+            // spaces after it.
+            let segment: Vec<char> = paragraph[segment_start..segment_end].chars().collect();
+            segment_start = segment_end;
+
+            // Trailing whitespace is allowed to hang past the edge of the line.
+            let mut advances = shaper.shape_from(&segment, line_width).char_advances;
+            let visible_width: f32 = advances[..trim_end(&segment).len()].iter().sum();
+            if line_width + visible_width <= max_width {
+                line.extend(segment);
+                line_width += advances.iter().sum::<f32>();
+            } else {
+                // Wrap before the segment, where tabs in it land on different tab stops.
+                if !line.is_empty() {
+                    lines.push(TextLine::wrapped(line));
+                    line = Vec::new();
+                    line_width = 0.0;
+                    advances = shaper.shape(&segment).char_advances;
+                }
+                // If the segment still doesn't fit on its own line, hard break it. The rest of
+                // the segment is shaped again from the start of each new line, so its tabs
+                // reach the new line's tab stops.
+                let mut rest_start = 0;
+                for (i, &c) in segment.iter().enumerate() {
+                    let advance = advances[i - rest_start];
+                    if !line.is_empty() && !c.is_whitespace() && line_width + advance > max_width {
+                        lines.push(TextLine::wrapped(line));
+                        line = Vec::new();
+                        line_width = 0.0;
+                        rest_start = i;
+                        advances = shaper.shape(&segment[rest_start..]).char_advances;
+                    }
+                    line.push(c);
+                    line_width += advances[i - rest_start];
+                }
+            }
+
+            if let BreakOpportunity::Mandatory = opportunity {
+                if segment_end < paragraph.len() {
+                    lines.push(TextLine {
+                        chars: line,
+                        hard_break: true,
+                    });
+                    line = Vec::new();
+                    line_width = 0.0;
+                }
+            }
+        }
+        lines.push(TextLine {
+            chars: line,
+            hard_break: true,
+        });
+    }
+    lines
+}
+
+/// Replace the end of a line with an ellipsis, removing characters until the ellipsis fits.
+fn add_ellipsis(shaper: &Shaper, line: &mut TextLine, max_width: f32) {
+    let advances = shaper.shape(&line.chars).char_advances;
+    let mut width: f32 = advances.iter().sum();
+    let mut len = line.chars.len();
+    loop {
+        while len > 0 && line.chars[len - 1].is_whitespace() {
+            len -= 1;
+            width -= advances[len];
+        }
+        let ellipsis_width = shaper.shape_from(&[ELLIPSIS], width).char_advances[0];
+        if len == 0 || width + ellipsis_width <= max_width {
+            break;
+        }
+        len -= 1;
+        width -= advances[len];
+    }
+    line.chars.truncate(len);
+    line.chars.push(ELLIPSIS);
+}
+
+fn trim_end(chars: &[char]) -> &[char] {
+    let visible_len = chars
+        .iter()
+        .rposition(|c| !c.is_whitespace())
+        .map_or(0, |i| i + 1);
+    &chars[..visible_len]
+}
+
+/// A line of positioned glyphs built up by layout_paragraph before it's aligned.
+struct LayoutLine {
+    glyphs: Vec<PositionedGlyph<'static>>,
+    /// The font each glyph came from.
+    fonts: Vec<FontHandle>,
+    /// Whether each glyph was made from whitespace, used to find spaces when justifying.
+    whitespace: Vec<bool>,
+    /// Where the line starts on its baseline.
+    origin: Point<f32>,
+    /// Width from the start of the line to the advance of its last non-whitespace glyph.
+    width: f32,
+    /// True when the line was ended by a line break in the text, rather than by wrapping.
+    hard_break: bool,
+}
+
+impl LayoutLine {
+    /// Position a line's glyphs along the baseline, starting at the baseline's point.
+    fn new(shaper: &Shaper, text_line: TextLine, baseline: Point<f32>) -> Self {
+        let shaped = shaper.shape(&text_line.chars);
+        let mut glyphs = Vec::with_capacity(shaped.glyphs.len());
+        let mut fonts = Vec::with_capacity(shaped.glyphs.len());
+        let mut whitespace = Vec::with_capacity(shaped.glyphs.len());
+        let mut width = 0.0;
+        for shaped_glyph in &shaped.glyphs {
+            let glyph = shaper.scaled_glyph(shaped_glyph).positioned(point(
+                baseline.x + shaped_glyph.x,
+                baseline.y - shaped_glyph.y,
+            ));
+            glyphs.push(glyph);
+            fonts.push(shaped_glyph.font);
+            whitespace.push(shaped_glyph.is_whitespace);
+            // Trailing spaces aren't counted so they don't push aligned text off center.
+            if !shaped_glyph.is_whitespace {
+                width = shaped_glyph.x + shaped_glyph.advance_width;
+            }
+        }
+        LayoutLine {
+            glyphs,
+            fonts,
+            whitespace,
+            origin: baseline,
+            width,
+            hard_break: text_line.hard_break,
+        }
+    }
+
+    fn shift_x(&mut self, x: f32) {
+        self.origin.x += x;
+        for glyph in &mut self.glyphs {
+            let mut new_pos = glyph.position();
+            new_pos.x += x;
+            glyph.set_position(new_pos);
+        }
+    }
+
+    fn shift_y(&mut self, y: f32) {
+        self.origin.y += y;
+        for glyph in &mut self.glyphs {
+            let mut new_pos = glyph.position();
+            new_pos.y += y;
+            glyph.set_position(new_pos);
+        }
+    }
+
+    /// Stretch the spaces between words so the line fills the paragraph width.
+    fn justify(&mut self, unused_line_width: f32) {
+        // Trailing spaces aren't between words, so they don't get stretched.
+        let visible_len = self
+            .whitespace
+            .iter()
+            .rposition(|&is_whitespace| !is_whitespace)
+            .map_or(0, |i| i + 1);
+        let gap_count = self.whitespace[..visible_len]
+            .iter()
+            .filter(|&&is_whitespace| is_whitespace)
+            .count();
+        if gap_count == 0 || unused_line_width <= 0.0 {
+            return;
+        }
+        self.width += unused_line_width;
+        let gap_width = unused_line_width / gap_count as f32;
+        let mut gaps_before = 0;
+        for (glyph, &is_whitespace) in self.glyphs.iter_mut().zip(&self.whitespace) {
+            if gaps_before > 0 {
+                let mut new_pos = glyph.position();
+                new_pos.x += gap_width * gaps_before as f32;
+                glyph.set_position(new_pos);
+            }
+            if is_whitespace {
+                gaps_before += 1;
+            }
+        }
+    }
+}
+
+/// How often LayoutCache could reuse a layout instead of laying text out again.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LayoutStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Remembers the glyphs of each entity's last layout so text that hasn't changed isn't laid out
+/// again every frame. Layouts are made at the origin so moving a text doesn't invalidate it.
+/// Layouts of texts that stop being drawn, like ones removed from the world, are forgotten at
+/// the end of the frame.
+#[derive(Default)]
+pub struct LayoutCache {
+    layouts: HashMap<usize, CachedLayout>,
+    stats: LayoutStats,
+    /// Counts up at the end of each frame.
+    frame: u64,
+}
+
+struct CachedLayout {
+    key: LayoutKey,
+    layout: TextLayout<'static>,
+    /// The last frame the layout was asked for in.
+    frame: u64,
+}
+
+/// Everything a layout depends on. If any of it changes the layout is made again.
+struct LayoutKey {
+    text: String,
+    alignment: TextAlignment,
+    vertical_alignment: VerticalAlignment,
+    max_lines: Option<usize>,
+    overflow: TextOverflow,
+    font: FontHandle,
+    scale_factor: f32,
+    width: f32,
+    height: f32,
+}
+
+impl LayoutKey {
+    fn new(
+        text: &TextComponent,
+        font: FontHandle,
+        scale_factor: f32,
+        width: f32,
+        height: f32,
+    ) -> Self {
+        LayoutKey {
+            text: text.text.clone(),
+            alignment: text.alignment,
+            vertical_alignment: text.vertical_alignment,
+            max_lines: text.max_lines,
+            overflow: text.overflow,
+            font,
+            scale_factor,
+            width,
+            height,
+        }
+    }
+
+    // Compared field by field so checking for a hit doesn't need to clone the text.
+    fn matches(
+        &self,
+        text: &TextComponent,
+        font: FontHandle,
+        scale_factor: f32,
+        width: f32,
+        height: f32,
+    ) -> bool {
+        self.text == text.text
+            && self.alignment == text.alignment
+            && self.vertical_alignment == text.vertical_alignment
+            && self.max_lines == text.max_lines
+            && self.overflow == text.overflow
+            && self.font == font
+            && self.scale_factor == scale_factor
+            && self.width == width
+            && self.height == height
+    }
+}
+
+impl LayoutCache {
+    pub fn new() -> Self {
+        LayoutCache {
+            layouts: HashMap::new(),
+            stats: LayoutStats::default(),
+            frame: 0,
+        }
+    }
+
+    /// Get an entity's layout in physical pixels, positioned relative to the top left of its
+    /// paragraph. The text is only laid out if it changed since the last time it was asked for.
+    /// Glyphs come from the first font in the chain that has them, and the first font is the
+    /// one the layout is remembered by, so the cache should be cleared if the rest changes.
+    /// Fails if no fonts are loaded.
+    pub fn layout(
+        &mut self,
+        entity: usize,
+        fonts: &FontRegistry,
+        font_chain: &[FontHandle],
+        scale_factor: f32,
+        text: &TextComponent,
+        transform: &TransformComponent,
+    ) -> Result<&TextLayout<'static>, FontError> {
+        let (width, height) = (transform.width, transform.height);
+        let font_handle = font_chain.first().copied().unwrap_or_default();
+        let is_hit = self.layouts.get(&entity).is_some_and(|cached| {
+            cached
+                .key
+                .matches(text, font_handle, scale_factor, width, height)
+        });
+        if is_hit {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+            // Text is laid out in physical pixels so glyphs are rasterized sharply.
+            let origin = Position { x: 0.0, y: 0.0 };
+            let scale = Scale::uniform(FONT_SIZE * scale_factor);
+            let shaper = Shaper::new(fonts, font_chain, scale)?;
+            let layout = layout_paragraph(
+                &shaper,
+                width * scale_factor,
+                Some(height * scale_factor),
+                text,
+                origin,
+            );
+            self.layouts.insert(
+                entity,
+                CachedLayout {
+                    key: LayoutKey::new(text, font_handle, scale_factor, width, height),
+                    layout,
+                    frame: self.frame,
+                },
+            );
+        }
+        let cached = self.layouts.get_mut(&entity).unwrap();
+        cached.frame = self.frame;
+        Ok(&cached.layout)
+    }
+
+    /// Forget the layouts that weren't asked for since the last end_frame, then start a new
+    /// frame.
+    pub fn end_frame(&mut self) {
+        let frame = self.frame;
+        self.layouts.retain(|_, cached| cached.frame == frame);
+        self.frame += 1;
+    }
+
+    /// How many layouts are remembered.
+    pub fn len(&self) -> usize {
+        self.layouts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layouts.is_empty()
+    }
+
+    pub fn stats(&self) -> LayoutStats {
+        self.stats
+    }
+
+    pub fn clear(&mut self) {
+        self.layouts.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::system::text::font::FontRegistry;
+
+    const ROBOTO: &[u8] = include_bytes!("../../../Roboto-Regular.ttf");
+    const ORIGIN: Position = Position { x: 10.0, y: 20.0 };
+
+    fn fonts() -> FontRegistry {
+        let mut fonts = FontRegistry::new();
+        fonts.load_static(ROBOTO).unwrap();
+        fonts
+    }
+
+    fn shaper(fonts: &FontRegistry) -> Shaper<'_> {
+        Shaper::new(fonts, &[FontHandle::default()], Scale::uniform(FONT_SIZE)).unwrap()
+    }
+
+    fn text(text: &str, alignment: TextAlignment) -> TextComponent {
+        TextComponent {
+            text: text.to_string(),
+            alignment,
+            ..Default::default()
+        }
+    }
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 0.01, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn an_empty_font_chain_falls_back_to_the_default_font() {
+        let fonts = fonts();
+        let mut cache = LayoutCache::new();
+        let text = text("Simon", TextAlignment::Left);
+        let layout = cache
+            .layout(0, &fonts, &[], 1.0, &text, &TransformComponent::new())
+            .unwrap();
+        assert_eq!(layout.glyphs.len(), 5);
+        assert!(layout
+            .fonts
+            .iter()
+            .all(|&font| font == FontHandle::default()));
+
+        let no_fonts = FontRegistry::new();
+        let scale = Scale::uniform(FONT_SIZE);
+        let shaper = Shaper::new(&no_fonts, &[FontHandle::default()], scale);
+        assert!(matches!(shaper, Err(FontError::NoFonts)));
+    }
+
+    #[test]
+    fn layouts_not_drawn_in_a_frame_are_forgotten() {
+        let fonts = fonts();
+        let mut cache = LayoutCache::new();
+        let chain = [FontHandle::default()];
+        let transform = TransformComponent::new();
+        let text = text("Simon", TextAlignment::Left);
+        for entity in 0..3 {
+            cache
+                .layout(entity, &fonts, &chain, 1.0, &text, &transform)
+                .unwrap();
+        }
+        cache.end_frame();
+        assert_eq!(cache.len(), 3);
+
+        // Entity 1 was removed, so only the others are drawn.
+        for entity in [0, 2] {
+            cache
+                .layout(entity, &fonts, &chain, 1.0, &text, &transform)
+                .unwrap();
+        }
+        cache.end_frame();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.stats().hits, 2);
+
+        cache.end_frame();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn left_aligned_glyphs_follow_the_shaped_line() {
+        let fonts = fonts();
+        let shaper = shaper(&fonts);
+        let layout = layout_paragraph(
+            &shaper,
+            500.0,
+            None,
+            &text("Simon AV", TextAlignment::Left),
+            ORIGIN,
+        );
+        let shaped = shaper.shape(&chars("Simon AV"));
+        let ascent = shaper.font().v_metrics(shaper.scale()).ascent;
+        assert_eq!(layout.glyphs.len(), shaped.glyphs.len());
+        for (glyph, shaped_glyph) in layout.glyphs.iter().zip(&shaped.glyphs) {
+            assert_close(glyph.position().x, ORIGIN.x + shaped_glyph.x);
+            assert_close(glyph.position().y, ORIGIN.y + ascent);
+        }
+        assert_eq!(layout.metrics.lines.len(), 1);
+        assert_close(
+            layout.metrics.lines[0].max.x,
+            ORIGIN.x + shaped.advance_width,
+        );
+    }
+
+    #[test]
+    fn glyph_rects_start_at_each_caret_offset() {
+        let fonts = fonts();
+        let shaper = shaper(&fonts);
+        let layout = layout_paragraph(
+            &shaper,
+            500.0,
+            None,
+            &text("a b", TextAlignment::Left),
+            ORIGIN,
+        );
+        let mut caret = ORIGIN.x;
+        for (glyph, rect) in layout.glyphs.iter().zip(&layout.metrics.glyphs) {
+            let advance_width = glyph.unpositioned().h_metrics().advance_width;
+            assert_close(rect.min.x, caret);
+            assert_close(rect.max.x, caret + advance_width);
+            caret += advance_width;
+        }
+        assert_eq!(layout.metrics.glyphs.len(), 3);
+    }
+
+    #[test]
+    fn lines_wrap_between_words_one_line_height_apart() {
+        let fonts = fonts();
+        let shaper = shaper(&fonts);
+        let width = shaper.width(&chars("three")) + 1.0;
+        let layout = layout_paragraph(
+            &shaper,
+            width,
+            None,
+            &text("one two three", TextAlignment::Left),
+            ORIGIN,
+        );
+        let v_metrics = shaper.font().v_metrics(shaper.scale());
+        let advance_height = v_metrics.ascent - v_metrics.descent + v_metrics.line_gap;
+        let lines = &layout.metrics.lines;
+        assert_eq!(lines.len(), 3);
+        for (i, line) in lines.iter().enumerate() {
+            assert_close(line.min.x, ORIGIN.x);
+            assert_close(line.min.y, ORIGIN.y + i as f32 * advance_height);
+            assert!(line.width() <= width);
+        }
+        // The spaces the lines wrapped at aren't drawn.
+        assert_eq!(layout.glyphs.len(), "onetwothree".len());
+    }
+
+    #[test]
+    fn long_words_break_where_they_reach_the_edge() {
+        let fonts = fonts();
+        let shaper = shaper(&fonts);
+        let width = shaper.width(&chars("mmmm")) + 1.0;
+        let layout = layout_paragraph(
+            &shaper,
+            width,
+            None,
+            &text("a mmmmmmmmmm", TextAlignment::Left),
+            ORIGIN,
+        );
+        let line_widths: Vec<f32> = layout.metrics.lines.iter().map(Rect::width).collect();
+        assert_eq!(line_widths.len(), 4);
+        assert_close(line_widths[1], shaper.width(&chars("mmmm")));
+        assert_close(line_widths[3], shaper.width(&chars("mm")));
+    }
+
+    #[test]
+    fn tabs_wrap_at_the_tab_stops_of_the_whole_line() {
+        let fonts = fonts();
+        let shaper = shaper(&fonts);
+        let line = chars("ab\tcd\tef");
+        let width = shaper.width(&line);
+        let layout = layout_paragraph(
+            &shaper,
+            width + 0.5,
+            None,
+            &text("ab\tcd\tef ab", TextAlignment::Left),
+            ORIGIN,
+        );
+        assert_eq!(layout.metrics.lines.len(), 2);
+        assert_close(layout.metrics.lines[0].width(), width);
+    }
+
+    #[test]
+    fn tabs_after_a_hard_break_reach_the_new_lines_tab_stops() {
+        let fonts = fonts();
+        let shaper = shaper(&fonts);
+        // The word is broken after nine is, so the tab comes after four is on the second line.
+        // The x only fits after it when the tab is measured from the second line's start, not
+        // from the start of the word.
+        let width = shaper.width(&chars("iiiiiiiii")) + 0.5;
+        assert!(shaper.width(&chars("iiii\tx")) <= width);
+        let layout = layout_paragraph(
+            &shaper,
+            width,
+            None,
+            &text("iiiiiiiiiiiii\tx", TextAlignment::Left),
+            ORIGIN,
+        );
+        assert_eq!(layout.metrics.lines.len(), 2);
+    }
+
+    #[test]
+    fn line_breaks_start_new_lines() {
+        let fonts = fonts();
+        let shaper = shaper(&fonts);
+        let layout = layout_paragraph(
+            &shaper,
+            500.0,
+            None,
+            &text("one\r\ntwo\n\nthree\rfour", TextAlignment::Left),
+            ORIGIN,
+        );
+        assert_eq!(layout.metrics.lines.len(), 5);
+        assert_close(layout.metrics.lines[2].width(), 0.0);
+    }
+
+    #[test]
+    fn max_lines_truncates_with_an_ellipsis() {
+        let fonts = fonts();
+        let shaper = shaper(&fonts);
+        let width = shaper.width(&chars("three")) + 1.0;
+        let text = TextComponent {
+            max_lines: Some(2),
+            overflow: TextOverflow::Ellipsis,
+            ..text("one two three", TextAlignment::Left)
+        };
+        let layout = layout_paragraph(&shaper, width, None, &text, ORIGIN);
+        assert_eq!(layout.metrics.lines.len(), 2);
+        let ellipsis = shaper.shape(&[ELLIPSIS]).glyphs[0].id;
+        assert_eq!(layout.glyphs.last().unwrap().id(), ellipsis);
+        assert!(layout.metrics.lines[1].width() <= width);
+    }
+
+    #[test]
+    fn no_lines_measure_as_an_empty_rect() {
+        let fonts = fonts();
+        let shaper = shaper(&fonts);
+        let text = TextComponent {
+            max_lines: Some(0),
+            vertical_alignment: VerticalAlignment::Middle,
+            ..text("Simon", TextAlignment::Justify)
+        };
+        let layout = layout_paragraph(&shaper, 300.0, Some(100.0), &text, ORIGIN);
+        assert!(layout.glyphs.is_empty());
+        assert!(layout.metrics.lines.is_empty());
+        let bounds = layout.metrics.bounds;
+        assert_eq!((bounds.min.x, bounds.min.y), (ORIGIN.x, ORIGIN.y));
+        assert_eq!((bounds.width(), bounds.height()), (0.0, 0.0));
+    }
+
+    #[test]
+    fn centered_and_right_aligned_lines_share_unused_width() {
+        let fonts = fonts();
+        let shaper = shaper(&fonts);
+        let line_width = shaper.width(&chars("Simon"));
+        let center = layout_paragraph(
+            &shaper,
+            300.0,
+            None,
+            &text("Simon", TextAlignment::Center),
+            ORIGIN,
+        );
+        let right = layout_paragraph(
+            &shaper,
+            300.0,
+            None,
+            &text("Simon", TextAlignment::Right),
+            ORIGIN,
+        );
+        assert_close(
+            center.metrics.lines[0].min.x,
+            ORIGIN.x + (300.0 - line_width) / 2.0,
+        );
+        assert_close(right.metrics.lines[0].min.x, ORIGIN.x + 300.0 - line_width);
+        assert_close(right.metrics.lines[0].max.x, ORIGIN.x + 300.0);
+        // Trailing spaces don't push a line off center.
+        let spaced = layout_paragraph(
+            &shaper,
+            300.0,
+            None,
+            &text("Simon   ", TextAlignment::Center),
+            ORIGIN,
+        );
+        assert_close(spaced.metrics.lines[0].min.x, center.metrics.lines[0].min.x);
+    }
+
+    #[test]
+    fn justified_lines_fill_the_width_except_the_last() {
+        let fonts = fonts();
+        let shaper = shaper(&fonts);
+        let width = shaper.width(&chars("one two three")) + 1.0;
+        let layout = layout_paragraph(
+            &shaper,
+            width,
+            None,
+            &text("one two three four", TextAlignment::Justify),
+            ORIGIN,
+        );
+        let lines = &layout.metrics.lines;
+        assert_eq!(lines.len(), 2);
+        assert_close(lines[0].width(), width);
+        assert_close(lines[1].width(), shaper.width(&chars("four")));
+        // Only the spaces between words are stretched, so the last word ends at the edge.
+        let four = layout.metrics.glyphs["one two three".len()];
+        assert_close(four.min.x, lines[1].min.x);
+        let end_of_three = layout.metrics.glyphs["one two three".len() - 1];
+        assert_close(end_of_three.max.x, ORIGIN.x + width);
+    }
+
+    #[test]
+    fn vertical_alignment_moves_text_inside_the_box() {
+        let fonts = fonts();
+        let shaper = shaper(&fonts);
+        let v_metrics = shaper.font().v_metrics(shaper.scale());
+        let text_height = v_metrics.ascent - v_metrics.descent;
+        let top_of = |vertical_alignment| {
+            let text = TextComponent {
+                vertical_alignment,
+                ..text("Simon", TextAlignment::Left)
+            };
+            layout_paragraph(&shaper, 300.0, Some(100.0), &text, ORIGIN)
+                .metrics
+                .bounds
+                .min
+                .y
+        };
+        assert_close(top_of(VerticalAlignment::Top), ORIGIN.y);
+        assert_close(
+            top_of(VerticalAlignment::Middle),
+            ORIGIN.y + (100.0 - text_height) / 2.0,
+        );
+        assert_close(
+            top_of(VerticalAlignment::Bottom),
+            ORIGIN.y + 100.0 - text_height,
+        );
+    }
+}
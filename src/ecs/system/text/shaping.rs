@@ -0,0 +1,421 @@
+use rusttype::{Font, GlyphId, Scale, ScaledGlyph};
+
+use super::font::{FontError, FontHandle, FontRegistry};
+
+/// Tab stops are this many spaces apart.
+const TAB_STOP_SPACES: f32 = 4.0;
+
+/// A glyph placed along a line by a Shaper.
+pub struct ShapedGlyph {
+    /// The font the glyph comes from, the first font in the chain that has it.
+    pub font: FontHandle,
+    pub id: GlyphId,
+    /// Where the glyph starts, from the start of the line.
+    pub x: f32,
+    /// How far the glyph is moved up from the baseline, like for combining marks.
+    pub y: f32,
+    pub advance_width: f32,
+    /// True when the glyph was made from whitespace, used to find spaces when justifying.
+    pub is_whitespace: bool,
+}
+
+/// A line of shaped glyphs in visual order, left to right.
+pub struct ShapedLine {
+    pub glyphs: Vec<ShapedGlyph>,
+    /// Where the caret ends up after the last glyph.
+    pub advance_width: f32,
+    /// How far each character moved the caret, in the order of the characters shaped. A glyph
+    /// made from several characters, like a ligature, counts towards the first of them.
+    pub char_advances: Vec<f32>,
+}
+
+/// One font in a Shaper's fallback chain.
+struct ShaperFont<'f> {
+    handle: FontHandle,
+    font: &'f Font<'static>,
+    #[cfg(feature = "shaping")]
+    face: Option<rustybuzz::Face<'f>>,
+}
+
+impl ShaperFont<'_> {
+    /// True if the font has a glyph for the character, rather than only .notdef.
+    fn covers(&self, c: char) -> bool {
+        self.font.glyph(c).id() != GlyphId(0)
+    }
+}
+
+/// Turns lines of characters into positioned glyph ids. With the shaping feature, text is
+/// shaped by rustybuzz, which handles ligatures, combining marks, and complex scripts, and
+/// right to left runs are reordered. Without it each character becomes one glyph, spaced by
+/// its advance width and kerning.
+///
+/// Each character's glyph comes from the first font in the chain that covers it. Characters no
+/// font covers are drawn with the first font's .notdef glyph.
+pub struct Shaper<'f> {
+    fonts: Vec<ShaperFont<'f>>,
+    scale: Scale,
+    tab_stop_width: f32,
+}
+
+impl<'f> Shaper<'f> {
+    /// Make a shaper for a chain of fonts, in the order they're tried. Handles of fonts that
+    /// aren't in the registry are skipped, and if none of them are, like with an empty chain,
+    /// the default font is used. Fails if that isn't loaded either.
+    pub fn new(
+        fonts: &'f FontRegistry,
+        font_chain: &[FontHandle],
+        scale: Scale,
+    ) -> Result<Self, FontError> {
+        let default_font = [FontHandle::default()];
+        let font_chain = if font_chain.iter().any(|&handle| fonts.get(handle).is_some()) {
+            font_chain
+        } else {
+            &default_font
+        };
+        let fonts: Vec<ShaperFont> = font_chain
+            .iter()
+            .filter_map(|&handle| {
+                let loaded = fonts.get_loaded(handle)?;
+                Some(ShaperFont {
+                    handle,
+                    font: &loaded.font,
+                    #[cfg(feature = "shaping")]
+                    face: rustybuzz::Face::from_slice(loaded.data, 0),
+                })
+            })
+            .collect();
+        let primary = fonts.first().ok_or(FontError::NoFonts)?;
+        let space_width = primary.font.glyph(' ').scaled(scale).h_metrics().advance_width;
+        Ok(Shaper {
+            fonts,
+            scale,
+            tab_stop_width: space_width * TAB_STOP_SPACES,
+        })
+    }
+
+    /// The first font in the chain. Line heights come from it.
+    pub fn font(&self) -> &'f Font<'static> {
+        self.fonts[0].font
+    }
+
+    pub fn scale(&self) -> Scale {
+        self.scale
+    }
+
+    /// A shaped glyph scaled from the font it was shaped with.
+    pub fn scaled_glyph(&self, glyph: &ShapedGlyph) -> ScaledGlyph<'static> {
+        let font = self
+            .fonts
+            .iter()
+            .find(|font| font.handle == glyph.font)
+            .unwrap_or(&self.fonts[0]);
+        font.font.glyph(glyph.id).scaled(self.scale)
+    }
+
+    /// The width of a line of characters.
+    pub fn width(&self, chars: &[char]) -> f32 {
+        self.shape(chars).advance_width
+    }
+
+    /// Shape a line of characters. A tab has no glyph of its own, so it's shaped as a space
+    /// that stretches to the next tab stop.
+    pub fn shape(&self, chars: &[char]) -> ShapedLine {
+        self.shape_from(chars, 0.0)
+    }
+
+    /// Shape characters as if they came after the start of a line, so tabs reach the same tab
+    /// stops they would in the whole line. Lets a line be measured a piece at a time.
+    pub fn shape_from(&self, chars: &[char], start: f32) -> ShapedLine {
+        #[cfg(feature = "shaping")]
+        if let Some(levels) = self.bidi_levels(chars) {
+            return self.shape_runs(chars, start, |run, first_char, line| {
+                let levels = &levels[first_char..first_char + run.len()];
+                self.shape_run(run, levels, first_char, line);
+            });
+        }
+        self.shape_runs(chars, start, |run, first_char, line| {
+            self.shape_run_simple(run, first_char, line);
+        })
+    }
+
+    /// Shape the runs of characters between tabs with shape_run, which is given each run and
+    /// where it starts in the line's characters.
+    fn shape_runs(
+        &self,
+        chars: &[char],
+        start: f32,
+        mut shape_run: impl FnMut(&[char], usize, &mut ShapedLine),
+    ) -> ShapedLine {
+        let mut line = ShapedLine {
+            glyphs: Vec::with_capacity(chars.len()),
+            advance_width: start,
+            char_advances: vec![0.0; chars.len()],
+        };
+        let mut run_start = 0;
+        for (i, run) in chars.split(|&c| c == '\t').enumerate() {
+            if i > 0 {
+                let primary = &self.fonts[0];
+                let start = line.advance_width;
+                let tab_stop = ((start / self.tab_stop_width).floor() + 1.0) * self.tab_stop_width;
+                line.glyphs.push(ShapedGlyph {
+                    font: primary.handle,
+                    id: primary.font.glyph(' ').id(),
+                    x: start,
+                    y: 0.0,
+                    advance_width: tab_stop - start,
+                    is_whitespace: true,
+                });
+                line.advance_width = tab_stop;
+                line.char_advances[run_start - 1] = tab_stop - start;
+            }
+            shape_run(run, run_start, &mut line);
+            run_start += run.len() + 1;
+        }
+        line
+    }
+
+    /// The bidi level of each of a line's characters. They're resolved for the whole line at
+    /// once, so the runs between tabs agree on which way the line goes. None if a font in the
+    /// chain can't be shaped with rustybuzz.
+    #[cfg(feature = "shaping")]
+    fn bidi_levels(&self, chars: &[char]) -> Option<Vec<unicode_bidi::Level>> {
+        if self.fonts.iter().any(|font| font.face.is_none()) {
+            return None;
+        }
+        // Bidi can't split up empty text.
+        if chars.is_empty() {
+            return Some(Vec::new());
+        }
+        let text: String = chars.iter().collect();
+        let bidi_info = unicode_bidi::ParagraphBidiInfo::new(&text, None);
+        Some(bidi_info.reordered_levels_per_char(0..text.len()))
+    }
+
+    /// Which font in the chain each character's glyph comes from. Whitespace stays in the
+    /// font of the character before it when it can, so runs aren't split up by spaces.
+    fn font_indices(&self, chars: &[char]) -> Vec<usize> {
+        let mut indices: Vec<usize> = Vec::with_capacity(chars.len());
+        for &c in chars {
+            let previous = indices.last().copied();
+            let index = match previous {
+                Some(previous) if c.is_whitespace() && self.fonts[previous].covers(c) => previous,
+                _ => self.fonts.iter().position(|font| font.covers(c)).unwrap_or(0),
+            };
+            indices.push(index);
+        }
+        indices
+    }
+
+    /// Shape a run of characters without tabs, starting at first_char in the line's
+    /// characters, with levels being their bidi levels.
+    #[cfg(feature = "shaping")]
+    fn shape_run(
+        &self,
+        chars: &[char],
+        levels: &[unicode_bidi::Level],
+        first_char: usize,
+        line: &mut ShapedLine,
+    ) {
+        let font_indices = self.font_indices(chars);
+        // Split the run into pieces of one font and direction, since a face can only shape its
+        // own glyphs, and shape them in the order they're seen so right to left pieces are
+        // reordered. A right to left piece's characters are seen last to first.
+        let mut pieces: Vec<std::ops::Range<usize>> = Vec::new();
+        for i in unicode_bidi::BidiInfo::reorder_visual(levels) {
+            match pieces.last_mut() {
+                Some(piece)
+                    if levels[i] == levels[piece.start]
+                        && font_indices[i] == font_indices[piece.start]
+                        && (i == piece.end || i + 1 == piece.start) =>
+                {
+                    *piece = piece.start.min(i)..piece.end.max(i + 1);
+                }
+                _ => pieces.push(i..i + 1),
+            }
+        }
+        for piece in pieces {
+            let direction = if levels[piece.start].is_rtl() {
+                rustybuzz::Direction::RightToLeft
+            } else {
+                rustybuzz::Direction::LeftToRight
+            };
+            let font = &self.fonts[font_indices[piece.start]];
+            let text: String = chars[piece.clone()].iter().collect();
+            // Which of the line's characters each byte of the text is from.
+            let byte_chars: Vec<usize> = piece
+                .clone()
+                .flat_map(|i| std::iter::repeat_n(first_char + i, chars[i].len_utf8()))
+                .collect();
+            self.shape_with_face(font, &text, &byte_chars, direction, line);
+        }
+    }
+
+    /// Shape text that's all in one font and direction, adding its glyphs to the line.
+    /// byte_chars is which of the line's characters each byte of the text is from.
+    #[cfg(feature = "shaping")]
+    fn shape_with_face(
+        &self,
+        font: &ShaperFont,
+        text: &str,
+        byte_chars: &[usize],
+        direction: rustybuzz::Direction,
+        line: &mut ShapedLine,
+    ) {
+        let face = match &font.face {
+            Some(face) => face,
+            None => return,
+        };
+        // rustybuzz positions are in font units. rusttype scales fonts so their height from
+        // descent to ascent is the scale, so the same is done here.
+        let v_metrics = font.font.v_metrics_unscaled();
+        let units_to_pixels = self.scale.y / (v_metrics.ascent - v_metrics.descent);
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.set_direction(direction);
+        let glyph_buffer = rustybuzz::shape(face, &[], buffer);
+        for (info, position) in glyph_buffer
+            .glyph_infos()
+            .iter()
+            .zip(glyph_buffer.glyph_positions())
+        {
+            let is_whitespace = text[info.cluster as usize..]
+                .chars()
+                .next()
+                .is_some_and(char::is_whitespace);
+            let advance_width = position.x_advance as f32 * units_to_pixels;
+            line.glyphs.push(ShapedGlyph {
+                font: font.handle,
+                id: GlyphId(info.glyph_id as u16),
+                x: line.advance_width + position.x_offset as f32 * units_to_pixels,
+                y: position.y_offset as f32 * units_to_pixels,
+                advance_width,
+                is_whitespace,
+            });
+            line.advance_width += advance_width;
+            line.char_advances[byte_chars[info.cluster as usize]] += advance_width;
+        }
+    }
+
+    /// One glyph per character, with kerning between glyphs from the same font.
+    fn shape_run_simple(&self, chars: &[char], first_char: usize, line: &mut ShapedLine) {
+        let mut last_glyph: Option<(usize, GlyphId)> = None;
+        let font_indices = self.font_indices(chars);
+        for (i, (&c, font_index)) in chars.iter().zip(font_indices).enumerate() {
+            let caret = line.advance_width;
+            let font = &self.fonts[font_index];
+            let base_glyph = font.font.glyph(c);
+            // If there's a previous glyph, add any kerning (additional h spacing) necessary
+            // between the previous and next glyph.
+            let id = base_glyph.id();
+            if let Some((last_font_index, last_id)) = last_glyph.take() {
+                if last_font_index == font_index {
+                    line.advance_width += font.font.pair_kerning(self.scale, last_id, id);
+                }
+            }
+            last_glyph = Some((font_index, id));
+
+            let advance_width = base_glyph.scaled(self.scale).h_metrics().advance_width;
+            line.glyphs.push(ShapedGlyph {
+                font: font.handle,
+                id,
+                x: line.advance_width,
+                y: 0.0,
+                advance_width,
+                is_whitespace: c.is_whitespace(),
+            });
+            // Position the caret ahead where the next glyph would go.
+            line.advance_width += advance_width;
+            line.char_advances[first_char + i] = line.advance_width - caret;
+        }
+    }
+}
+
+#[cfg(all(test, feature = "shaping"))]
+mod tests {
+    use super::*;
+    use crate::ecs::system::text::layout::FONT_SIZE;
+
+    const ROBOTO: &[u8] = include_bytes!("../../../Roboto-Regular.ttf");
+    /// Has Hebrew, which Roboto doesn't.
+    const DEJAVU_SANS: &[u8] = include_bytes!("../../../../tests/fonts/DejaVuSans.ttf");
+
+    /// Roboto with DejaVu Sans to fall back to.
+    fn fonts() -> (FontRegistry, Vec<FontHandle>) {
+        let mut fonts = FontRegistry::new();
+        let chain = vec![
+            fonts.load_static(ROBOTO).unwrap(),
+            fonts.load_static(DEJAVU_SANS).unwrap(),
+        ];
+        (fonts, chain)
+    }
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    /// Where the glyph for a character is in a shaped line, going left to right.
+    fn glyph_index(shaper: &Shaper, line: &ShapedLine, c: char) -> usize {
+        let font = shaper.fonts.iter().find(|font| font.covers(c)).unwrap();
+        let id = font.font.glyph(c).id();
+        line.glyphs
+            .iter()
+            .position(|glyph| glyph.font == font.handle && glyph.id == id)
+            .unwrap_or_else(|| panic!("no glyph for {:?}", c))
+    }
+
+    #[test]
+    fn right_to_left_text_is_reordered() {
+        let (fonts, chain) = fonts();
+        let shaper = Shaper::new(&fonts, &chain, Scale::uniform(FONT_SIZE)).unwrap();
+        // The line starts with Hebrew so it goes right to left, and the words are drawn in
+        // reverse: "def ב abc א".
+        let line = shaper.shape(&chars("\u{5d0} abc \u{5d1} def"));
+        let order: Vec<usize> = ['d', 'f', '\u{5d1}', 'a', 'c', '\u{5d0}']
+            .iter()
+            .map(|&c| glyph_index(&shaper, &line, c))
+            .collect();
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        assert_eq!(order, sorted);
+        for pair in line.glyphs.windows(2) {
+            assert!(pair[0].x < pair[1].x);
+        }
+    }
+
+    #[test]
+    fn text_after_a_tab_goes_the_same_way_as_the_line() {
+        let (fonts, chain) = fonts();
+        let shaper = Shaper::new(&fonts, &chain, Scale::uniform(FONT_SIZE)).unwrap();
+        // "abc" alone would go left to right, but the line goes right to left, so the Hebrew
+        // after the tab is drawn before it.
+        let line = shaper.shape(&chars("\u{5d0}\tabc \u{5d1}"));
+        assert!(glyph_index(&shaper, &line, '\u{5d1}') < glyph_index(&shaper, &line, 'a'));
+        // The tab still reaches the first tab stop.
+        assert_eq!(line.char_advances.len(), 7);
+        let tab_end = line.glyphs[1].x + line.glyphs[1].advance_width;
+        assert!((tab_end - shaper.tab_stop_width).abs() < 0.01);
+    }
+
+    #[test]
+    fn shaping_kerns_and_makes_ligatures() {
+        let mut fonts = FontRegistry::new();
+        fonts.load_static(ROBOTO).unwrap();
+        let shaper = Shaper::new(&fonts, &[], Scale::uniform(FONT_SIZE)).unwrap();
+        for text in ["AV", "fi"] {
+            let text = chars(text);
+            let shaped = shaper.shape(&text);
+            let mut simple = ShapedLine {
+                glyphs: Vec::new(),
+                advance_width: 0.0,
+                char_advances: vec![0.0; text.len()],
+            };
+            shaper.shape_run_simple(&text, 0, &mut simple);
+            assert!(
+                shaped.glyphs.len() < simple.glyphs.len()
+                    || shaped.advance_width < simple.advance_width,
+                "{:?} shaped the same as without shaping",
+                text
+            );
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::time::Duration;
+
+use crate::ecs::component::timer::TimerComponent;
+use crate::ecs::World;
+
+/// A timer's event, from the entity the timer is on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimerEvent<E> {
+    pub entity: usize,
+    pub event: E,
+}
+
+/// Runs every entity's timers. Give it the game clock's delta so timers freeze while the game
+/// is paused.
+pub struct TimerSystem {}
+
+impl TimerSystem {
+    pub fn new() -> Self {
+        TimerSystem {}
+    }
+
+    /// Run every TimerComponent<E> forward and return the events of the timers that fired, in
+    /// entity order. Call once per event type.
+    pub fn update<E: Clone + 'static>(
+        &mut self,
+        world: &World,
+        delta: Duration,
+    ) -> Vec<TimerEvent<E>> {
+        let mut events = Vec::new();
+        let Some(mut timer_components) = world.borrow_component_vec::<TimerComponent<E>>() else {
+            return events;
+        };
+        for (entity, timer_component) in timer_components.iter_mut().enumerate() {
+            let Some(timer_component) = timer_component else {
+                continue;
+            };
+            for timer in timer_component.timers.iter_mut() {
+                for _ in 0..timer.tick(delta) {
+                    events.push(TimerEvent {
+                        entity,
+                        event: timer.event.clone(),
+                    });
+                }
+            }
+            timer_component.timers.retain(|timer| !timer.is_finished());
+        }
+        events
+    }
+}
+
+impl Default for TimerSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::GameClock;
+    use crate::ecs::component::timer::Timer;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Event {
+        Beep,
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    /// A world with one entity that has a timer.
+    fn world_with(timer: Timer<Event>) -> World {
+        let mut world = World::new();
+        let entity = world.new_entity();
+        world.add_component_to_entity(entity, TimerComponent::new(timer));
+        world
+    }
+
+    fn timer_count(world: &mut World) -> usize {
+        world
+            .borrow_component::<TimerComponent<Event>>(0)
+            .unwrap()
+            .timers
+            .len()
+    }
+
+    #[test]
+    fn a_one_shot_timer_fires_once_and_is_removed() {
+        let mut world = world_with(Timer::once(ms(100), Event::Beep));
+        let mut timer_system = TimerSystem::new();
+        assert!(timer_system.update::<Event>(&world, ms(60)).is_empty());
+        let events = timer_system.update::<Event>(&world, ms(60));
+        assert_eq!(
+            events,
+            vec![TimerEvent {
+                entity: 0,
+                event: Event::Beep
+            }]
+        );
+        assert_eq!(timer_count(&mut world), 0);
+        assert!(timer_system.update::<Event>(&world, ms(500)).is_empty());
+    }
+
+    #[test]
+    fn a_one_shot_timer_fires_once_given_a_long_delta() {
+        let world = world_with(Timer::once(ms(100), Event::Beep));
+        let mut timer_system = TimerSystem::new();
+        assert_eq!(timer_system.update::<Event>(&world, ms(1000)).len(), 1);
+    }
+
+    #[test]
+    fn a_repeating_timer_fires_for_each_duration_in_a_long_delta() {
+        let mut world = world_with(Timer::repeating(ms(100), Event::Beep));
+        let mut timer_system = TimerSystem::new();
+        // 350ms fires three times, and the 50ms left over counts towards the next fire.
+        assert_eq!(timer_system.update::<Event>(&world, ms(350)).len(), 3);
+        assert_eq!(timer_system.update::<Event>(&world, ms(40)).len(), 0);
+        assert_eq!(timer_system.update::<Event>(&world, ms(10)).len(), 1);
+        assert_eq!(timer_count(&mut world), 1);
+    }
+
+    #[test]
+    fn timers_dont_run_while_the_clock_is_paused() {
+        let mut world = world_with(Timer::once(ms(100), Event::Beep));
+        let mut timer_system = TimerSystem::new();
+        let mut clock = GameClock::new();
+        clock.tick(ms(60));
+        assert!(timer_system
+            .update::<Event>(&world, clock.delta())
+            .is_empty());
+
+        clock.pause();
+        clock.tick(ms(1000));
+        assert!(timer_system
+            .update::<Event>(&world, clock.delta())
+            .is_empty());
+        let remaining = world
+            .borrow_component::<TimerComponent<Event>>(0)
+            .unwrap()
+            .timers[0]
+            .remaining();
+        assert_eq!(remaining, ms(40));
+
+        clock.resume();
+        clock.tick(ms(40));
+        assert_eq!(timer_system.update::<Event>(&world, clock.delta()).len(), 1);
+    }
+
+    #[test]
+    fn a_paused_timer_doesnt_fire() {
+        let mut timer = Timer::repeating(ms(100), Event::Beep);
+        timer.pause();
+        let world = world_with(timer);
+        let mut timer_system = TimerSystem::new();
+        assert!(timer_system.update::<Event>(&world, ms(1000)).is_empty());
+    }
+}
@@ -0,0 +1,271 @@
+use std::time::Duration;
+
+use crate::ecs::component::sprite::SpriteComponent;
+use crate::ecs::component::text::TextComponent;
+use crate::ecs::component::transform::{Position, TransformComponent};
+use crate::ecs::component::tween::{TweenComponent, TweenProperty};
+use crate::ecs::World;
+
+/// Something that happened to a tween during TweenSystem::update.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TweenEvent {
+    /// A looping tween finished a play and started the next.
+    Looped { entity: usize, label: &'static str },
+    /// A tween finished its last play and was removed.
+    Finished { entity: usize, label: &'static str },
+}
+
+/// Plays every entity's tweens. Time only moves when update is called, by however much it's
+/// given, so the same steps always animate the same way.
+pub struct TweenSystem {}
+
+impl TweenSystem {
+    pub fn new() -> Self {
+        TweenSystem {}
+    }
+
+    /// Move every tween forward by the time since the last frame and set the values they
+    /// animate. Returns what happened, in entity order.
+    pub fn update(&mut self, world: &World, delta: Duration) -> Vec<TweenEvent> {
+        let mut events = Vec::new();
+        let Some(mut tween_components) = world.borrow_component_vec::<TweenComponent>() else {
+            return events;
+        };
+        let mut transforms = world.borrow_component_vec::<TransformComponent>();
+        let mut sprites = world.borrow_component_vec::<SpriteComponent>();
+        let mut texts = world.borrow_component_vec::<TextComponent>();
+
+        for (entity, tween_component) in tween_components.iter_mut().enumerate() {
+            let Some(tween_component) = tween_component else {
+                continue;
+            };
+            for tween in tween_component.tweens.iter_mut() {
+                let (progress, finished_plays) = tween.advance(delta);
+                if let Some(progress) = progress {
+                    let transform = transforms
+                        .as_mut()
+                        .and_then(|transforms| transforms[entity].as_mut());
+                    let sprite = sprites
+                        .as_mut()
+                        .and_then(|sprites| sprites[entity].as_mut());
+                    let text = texts.as_mut().and_then(|texts| texts[entity].as_mut());
+                    match tween.property {
+                        TweenProperty::Position { from, to } => {
+                            if let Some(transform) = transform {
+                                transform.position = Position {
+                                    x: lerp(from.x, to.x, progress),
+                                    y: lerp(from.y, to.y, progress),
+                                };
+                            }
+                        }
+                        TweenProperty::Width { from, to } => {
+                            if let Some(transform) = transform {
+                                transform.width = lerp(from, to, progress);
+                            }
+                        }
+                        TweenProperty::SpriteColour { from, to } => {
+                            if let Some(sprite) = sprite {
+                                sprite.colour = lerp_colour(from, to, progress);
+                            }
+                        }
+                        TweenProperty::TextColour { from, to } => {
+                            if let Some(text) = text {
+                                text.colour = lerp_colour(from, to, progress);
+                            }
+                        }
+                    }
+                }
+
+                // The last finished play is the end of the tween, the others are loops.
+                let loops = if tween.is_finished() {
+                    finished_plays.saturating_sub(1)
+                } else {
+                    finished_plays
+                };
+                for _ in 0..loops {
+                    events.push(TweenEvent::Looped {
+                        entity,
+                        label: tween.label,
+                    });
+                }
+                // Tweens that play no times finish on their first update.
+                let plays_none = tween.looping.plays() == Some(0);
+                if tween.is_finished() && (finished_plays > 0 || plays_none) {
+                    events.push(TweenEvent::Finished {
+                        entity,
+                        label: tween.label,
+                    });
+                }
+            }
+            tween_component.tweens.retain(|tween| !tween.is_finished());
+        }
+        events
+    }
+}
+
+impl Default for TweenSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn lerp(from: f32, to: f32, t: f32) -> f32 {
+    from + (to - from) * t
+}
+
+fn lerp_colour(from: [f32; 4], to: [f32; 4], t: f32) -> [f32; 4] {
+    std::array::from_fn(|channel| lerp(from[channel], to[channel], t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::component::tween::{Easing, Looping, Tween};
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    /// A world with one entity whose width is tweened from 0 to 100.
+    fn world_with(tween: Tween) -> World {
+        let mut world = World::new();
+        let entity = world.new_entity();
+        world.add_component_to_entity(entity, TweenComponent::new(tween));
+        world
+    }
+
+    fn width_tween(duration: Duration) -> Tween {
+        Tween::new(
+            TweenProperty::Width {
+                from: 0.0,
+                to: 100.0,
+            },
+            duration,
+        )
+        .label("width")
+    }
+
+    fn assert_width(world: &mut World, expected: f32) {
+        let width = world
+            .borrow_component::<TransformComponent>(0)
+            .unwrap()
+            .width;
+        assert!(
+            (width - expected).abs() < 0.001,
+            "{} != {}",
+            width,
+            expected
+        );
+    }
+
+    fn tween_count(world: &mut World) -> usize {
+        world
+            .borrow_component::<TweenComponent>(0)
+            .unwrap()
+            .tweens
+            .len()
+    }
+
+    #[test]
+    fn a_tween_moves_its_value_and_finishes_at_the_end() {
+        let mut world = world_with(width_tween(ms(100)));
+        let mut tween_system = TweenSystem::new();
+        assert!(tween_system.update(&world, ms(25)).is_empty());
+        assert_width(&mut world, 25.0);
+        assert!(tween_system.update(&world, ms(50)).is_empty());
+        assert_width(&mut world, 75.0);
+
+        let events = tween_system.update(&world, ms(25));
+        assert_eq!(
+            events,
+            [TweenEvent::Finished {
+                entity: 0,
+                label: "width"
+            }]
+        );
+        assert_width(&mut world, 100.0);
+        assert_eq!(tween_count(&mut world), 0);
+    }
+
+    #[test]
+    fn stepping_past_the_end_stops_on_the_end_value() {
+        let mut world = world_with(width_tween(ms(100)).easing(Easing::EaseIn));
+        let events = TweenSystem::new().update(&world, ms(250));
+        assert_eq!(events.len(), 1);
+        assert_width(&mut world, 100.0);
+    }
+
+    #[test]
+    fn a_delayed_tween_leaves_its_value_alone_until_it_starts() {
+        let mut world = world_with(width_tween(ms(100)).delay(ms(50)));
+        world
+            .borrow_component::<TransformComponent>(0)
+            .unwrap()
+            .width = 42.0;
+        let mut tween_system = TweenSystem::new();
+        tween_system.update(&world, ms(40));
+        assert_width(&mut world, 42.0);
+        tween_system.update(&world, ms(20));
+        assert_width(&mut world, 10.0);
+    }
+
+    #[test]
+    fn looping_tweens_wrap_around_and_send_an_event_per_loop() {
+        let mut world = world_with(width_tween(ms(100)).looping(Looping::Times(3)));
+        let mut tween_system = TweenSystem::new();
+        let looped = TweenEvent::Looped {
+            entity: 0,
+            label: "width",
+        };
+        assert_eq!(tween_system.update(&world, ms(130)), [looped]);
+        assert_width(&mut world, 30.0);
+        // Two loops end in one step, the second being the last play.
+        assert_eq!(
+            tween_system.update(&world, ms(200)),
+            [
+                looped,
+                TweenEvent::Finished {
+                    entity: 0,
+                    label: "width"
+                }
+            ]
+        );
+        assert_width(&mut world, 100.0);
+        assert_eq!(tween_count(&mut world), 0);
+    }
+
+    #[test]
+    fn a_tween_that_plays_no_times_finishes_without_moving_its_value() {
+        let mut world = world_with(width_tween(ms(100)).looping(Looping::Times(0)));
+        world
+            .borrow_component::<TransformComponent>(0)
+            .unwrap()
+            .width = 42.0;
+        let events = TweenSystem::new().update(&world, ms(10));
+        assert_eq!(
+            events,
+            [TweenEvent::Finished {
+                entity: 0,
+                label: "width"
+            }]
+        );
+        assert_width(&mut world, 42.0);
+        assert_eq!(tween_count(&mut world), 0);
+    }
+
+    #[test]
+    fn yoyo_tweens_play_every_other_loop_backwards() {
+        let mut world = world_with(width_tween(ms(100)).looping(Looping::Forever).yoyo());
+        let mut tween_system = TweenSystem::new();
+        tween_system.update(&world, ms(75));
+        assert_width(&mut world, 75.0);
+        tween_system.update(&world, ms(50));
+        assert_width(&mut world, 75.0);
+        tween_system.update(&world, ms(50));
+        assert_width(&mut world, 25.0);
+        // 1175ms in is three quarters into the twelfth play, which is backwards.
+        tween_system.update(&world, ms(1000));
+        assert_width(&mut world, 25.0);
+        assert_eq!(tween_count(&mut world), 1);
+    }
+}
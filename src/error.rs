@@ -0,0 +1,130 @@
+use glium::backend::glutin::DisplayCreationError;
+use std::fmt;
+
+#[cfg(feature = "audio")]
+use crate::ecs::system::audio::AudioError;
+use crate::ecs::system::localization::LocalizationError;
+#[cfg(target_os = "linux")]
+use crate::ecs::system::render::headless::HeadlessError;
+use crate::ecs::system::render::image::ImageError;
+use crate::ecs::system::render::RenderError;
+use crate::ecs::system::text::font::FontError;
+use crate::persistence::PersistenceError;
+use crate::settings::SettingsError;
+
+/// Anything that can stop the game from starting or running, from any of its systems.
+#[derive(Debug)]
+pub enum Error {
+    /// There's no X11 or Wayland display server to open a window on, like over ssh or on CI.
+    NoDisplayServer,
+    /// The window or its opengl context couldn't be made.
+    DisplayCreation(DisplayCreationError),
+    #[cfg(target_os = "linux")]
+    Headless(HeadlessError),
+    Render(RenderError),
+    Font(FontError),
+    Localization(LocalizationError),
+    Settings(SettingsError),
+    Persistence(PersistenceError),
+    Image(ImageError),
+    #[cfg(feature = "audio")]
+    Audio(AudioError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoDisplayServer => write!(
+                f,
+                "no display server to open a window on, neither DISPLAY nor WAYLAND_DISPLAY is set"
+            ),
+            Error::DisplayCreation(err) => write!(f, "could not create window: {}", err),
+            #[cfg(target_os = "linux")]
+            Error::Headless(err) => write!(f, "{}", err),
+            Error::Render(err) => write!(f, "{}", err),
+            Error::Font(err) => write!(f, "{}", err),
+            Error::Localization(err) => write!(f, "{}", err),
+            Error::Settings(err) => write!(f, "{}", err),
+            Error::Persistence(err) => write!(f, "{}", err),
+            Error::Image(err) => write!(f, "{}", err),
+            #[cfg(feature = "audio")]
+            Error::Audio(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        // Each error's message is already shown by Display, so skip to its source.
+        match self {
+            Error::NoDisplayServer => None,
+            Error::DisplayCreation(err) => std::error::Error::source(err),
+            #[cfg(target_os = "linux")]
+            Error::Headless(err) => std::error::Error::source(err),
+            Error::Render(err) => std::error::Error::source(err),
+            Error::Font(err) => std::error::Error::source(err),
+            Error::Localization(err) => std::error::Error::source(err),
+            Error::Settings(err) => std::error::Error::source(err),
+            Error::Persistence(err) => std::error::Error::source(err),
+            Error::Image(err) => std::error::Error::source(err),
+            #[cfg(feature = "audio")]
+            Error::Audio(err) => std::error::Error::source(err),
+        }
+    }
+}
+
+impl From<DisplayCreationError> for Error {
+    fn from(err: DisplayCreationError) -> Self {
+        Error::DisplayCreation(err)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<HeadlessError> for Error {
+    fn from(err: HeadlessError) -> Self {
+        Error::Headless(err)
+    }
+}
+
+impl From<RenderError> for Error {
+    fn from(err: RenderError) -> Self {
+        Error::Render(err)
+    }
+}
+
+impl From<FontError> for Error {
+    fn from(err: FontError) -> Self {
+        Error::Font(err)
+    }
+}
+
+impl From<LocalizationError> for Error {
+    fn from(err: LocalizationError) -> Self {
+        Error::Localization(err)
+    }
+}
+
+impl From<SettingsError> for Error {
+    fn from(err: SettingsError) -> Self {
+        Error::Settings(err)
+    }
+}
+
+impl From<PersistenceError> for Error {
+    fn from(err: PersistenceError) -> Self {
+        Error::Persistence(err)
+    }
+}
+
+impl From<ImageError> for Error {
+    fn from(err: ImageError) -> Self {
+        Error::Image(err)
+    }
+}
+
+#[cfg(feature = "audio")]
+impl From<AudioError> for Error {
+    fn from(err: AudioError) -> Self {
+        Error::Audio(err)
+    }
+}
@@ -0,0 +1,135 @@
+use crate::ecs::component::text::{TextAlignment, TextComponent, TextMessage};
+use crate::ecs::component::transform::{Anchor, TransformComponent};
+use crate::ecs::World;
+use crate::persistence::HighScores;
+
+/// How far down the first row of scores is, and how far apart rows are.
+const FIRST_ROW_Y: f32 = 90.0;
+const ROW_HEIGHT: f32 = 32.0;
+
+/// Add the high score screen's entities to the world: a heading and a row per score with the
+/// player's place and name, their score, the difficulty, and the date. Headings use message
+/// keys, so a LocalizationSystem has to fill them in. Returns the entities it made, to remove
+/// when leaving the screen.
+pub fn setup(world: &mut World, high_scores: &HighScores) -> Vec<usize> {
+    // Setup heading
+    let mut entities = vec![add_text(
+        world,
+        TextComponent {
+            message: Some(TextMessage::new("high-scores")),
+            alignment: TextAlignment::Center,
+            ..Default::default()
+        },
+        (0.0, 20.0),
+        300.0,
+    )];
+
+    if high_scores.is_empty() {
+        entities.push(add_text(
+            world,
+            TextComponent {
+                message: Some(TextMessage::new("no-high-scores")),
+                alignment: TextAlignment::Center,
+                ..Default::default()
+            },
+            (0.0, FIRST_ROW_Y),
+            500.0,
+        ));
+        return entities;
+    }
+
+    // Setup a row per score, in columns centered on the window.
+    for (place, high_score) in high_scores.iter().enumerate() {
+        let y = FIRST_ROW_Y + place as f32 * ROW_HEIGHT;
+        entities.push(add_text(
+            world,
+            TextComponent {
+                text: format!("{}. {}", place + 1, high_score.name),
+                max_lines: Some(1),
+                ..Default::default()
+            },
+            (-150.0, y),
+            160.0,
+        ));
+        entities.push(add_text(
+            world,
+            TextComponent {
+                text: high_score.score.to_string(),
+                alignment: TextAlignment::Right,
+                ..Default::default()
+            },
+            (-30.0, y),
+            70.0,
+        ));
+        entities.push(add_text(
+            world,
+            TextComponent {
+                message: Some(TextMessage::new(high_score.difficulty.message_key())),
+                alignment: TextAlignment::Center,
+                ..Default::default()
+            },
+            (65.0, y),
+            110.0,
+        ));
+        entities.push(add_text(
+            world,
+            TextComponent {
+                text: high_score.date.to_string(),
+                alignment: TextAlignment::Right,
+                ..Default::default()
+            },
+            (185.0, y),
+            130.0,
+        ));
+    }
+    entities
+}
+
+/// Add a text entity centered at a position from the top middle of the window.
+fn add_text(world: &mut World, text: TextComponent, (x, y): (f32, f32), width: f32) -> usize {
+    let entity = world.new_entity();
+    world.add_component_to_entity(entity, text);
+    let transform = world
+        .borrow_component::<TransformComponent>(entity)
+        .unwrap();
+    transform.width = width;
+    transform.anchor = Anchor::TopMiddle;
+    transform.position.x = x;
+    transform.position.y = y;
+    entity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::{Difficulty, HighScore};
+
+    fn text_count(world: &World) -> usize {
+        world
+            .borrow_component_vec::<TextComponent>()
+            .map_or(0, |texts| texts.iter().flatten().count())
+    }
+
+    #[test]
+    fn the_screen_has_a_row_per_score() {
+        let mut high_scores = HighScores::default();
+        high_scores.add(HighScore::new("Ada", 12, Difficulty::Normal));
+        high_scores.add(HighScore::new("Grace", 9, Difficulty::Hard));
+        let mut world = World::new();
+        let entities = setup(&mut world, &high_scores);
+        // The heading, then four columns a row.
+        assert_eq!(entities.len(), 9);
+        assert_eq!(text_count(&world), 9);
+    }
+
+    #[test]
+    fn removing_the_screens_entities_leaves_no_text() {
+        let mut world = World::new();
+        let entities = setup(&mut world, &HighScores::default());
+        assert_eq!(text_count(&world), 2);
+        for entity in entities {
+            world.remove_entity(entity);
+        }
+        assert_eq!(text_count(&world), 0);
+    }
+}
@@ -0,0 +1,7 @@
+pub mod clock;
+pub mod ecs;
+pub mod error;
+pub mod high_score_screen;
+pub mod persistence;
+pub mod settings;
+pub mod title_screen;
@@ -1,63 +1,101 @@
-mod ecs;
-use ecs::component::text::{TextAlignment, TextComponent};
-use ecs::component::transform::{Anchor, TransformComponent};
+#[cfg(feature = "audio")]
+use ecs::system::audio::{AudioOutput, AudioSystem};
 use ecs::system::input::{InputSystem, SampleContext};
+use ecs::system::localization::LocalizationSystem;
+use ecs::system::render::glium_renderer::GliumRenderer;
+use ecs::system::render::{RenderError, RenderSystem, Renderer};
+use ecs::system::sprite::SpriteSystem;
 use ecs::system::text::TextSystem;
-use glium::{
-    glutin::{self, event::Event},
-    Surface,
-};
+use ecs::system::timer::TimerSystem;
+use ecs::system::tween::TweenSystem;
+use glium::backend::glutin::DisplayCreationError;
+use glium::glutin;
+use glium::glutin::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
+use glium::glutin::event_loop::EventLoopWindowTarget;
+use glium::Display;
+use rust_simon_says::clock::GameClock;
+use rust_simon_says::error::Error;
+use rust_simon_says::persistence::{self, SaveData};
+use rust_simon_says::settings::{FullscreenMode, Settings, SETTINGS_PATH};
+use rust_simon_says::title_screen::TitleScreenEvent;
+use rust_simon_says::{ecs, high_score_screen, title_screen};
+use std::path::PathBuf;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() {
+    // Print what went wrong and why instead of panicking, like when there's no display or the
+    // gpu's opengl is too old.
+    if let Err(err) = run() {
+        eprintln!("rust simon says could not start: {}", err);
+        print_sources(&err);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Error> {
+    // Settings and high scores are saved together in the platform's data directory. Without
+    // one the game still runs, it just can't save.
+    let (mut save_data, save_path) = match persistence::save_path() {
+        Ok(path) => load_save_data(path),
+        Err(err) => {
+            eprintln!("{}, settings and high scores won't be saved", err);
+            (SaveData::default(), None)
+        }
+    };
+    let mut settings = save_data.settings.clone();
+
+    // Winit aborts making the event loop when there's no display server, so check first.
+    #[cfg(target_os = "linux")]
+    {
+        let is_set = |name| std::env::var_os(name).is_some_and(|value| !value.is_empty());
+        if !is_set("DISPLAY") && !is_set("WAYLAND_DISPLAY") {
+            return Err(Error::NoDisplayServer);
+        }
+    }
     let event_loop = glutin::event_loop::EventLoop::new();
-    let wb = glutin::window::WindowBuilder::new()
-        .with_inner_size(glium::glutin::dpi::PhysicalSize::new(512, 512))
-        .with_title("Rust Simon Says");
-    let cb = glutin::ContextBuilder::new();
-    let display = glium::Display::new(wb, cb, &event_loop).unwrap();
+    let mut display = create_display(&settings, &event_loop)?;
 
     let mut world = ecs::World::new();
-    let mut text_system = TextSystem::new(&display);
+    let mut renderer = GliumRenderer::new(&display)?;
+    eprintln!("compiling shaders as {}", renderer.shader_version());
+    renderer.set_msaa(settings.display.msaa);
+    let mut text_system = TextSystem::new()?;
+    let mut sprite_system = SpriteSystem::new();
+    let mut render_system = RenderSystem::new();
     let mut input_system = InputSystem::new();
+    let mut tween_system = TweenSystem::new();
+    let mut timer_system = TimerSystem::new();
+    #[cfg(feature = "audio")]
+    let mut audio_system = AudioSystem::new(audio_output());
+    #[cfg(feature = "audio")]
+    let pad_sounds = title_screen::load_pad_sounds(&mut audio_system);
+
+    // Load every locale's messages and pick one from the LANG environment variable, like
+    // "fr_CA.UTF-8", falling back to english.
+    let mut localization_system = LocalizationSystem::new("en");
+    localization_system.load_builtin_catalogs()?;
+    if let Ok(lang) = std::env::var("LANG") {
+        let language = lang.split(['_', '.']).next().unwrap_or_default();
+        if localization_system.locales().any(|locale| locale == language) {
+            localization_system.set_locale(language);
+        }
+    }
+
     let sample_context = SampleContext {};
     input_system.set_context(Box::new(sample_context));
 
-    // Setup title
-    let title_entity = world.new_entity();
-    world.add_component_to_entity(
-        title_entity,
-        TextComponent {
-            text: "Simon Says".to_string(),
-            alignment: TextAlignment::Center,
-        },
-    );
-    let title_transform = world
-        .borrow_component::<TransformComponent>(title_entity)
-        .unwrap();
-    title_transform.width = 300.0;
-    title_transform.anchor = Anchor::TopMiddle;
-    title_transform.position.y = 20.0;
-
-    // Setup subtitle
-    let start_button = world.new_entity();
-    world.add_component_to_entity(
-        start_button,
-        TextComponent {
-            alignment: TextAlignment::Center,
-            text: "Press Enter to Play".to_string(),
-        },
-    );
-    let start_button_transform = world
-        .borrow_component::<TransformComponent>(start_button)
-        .unwrap();
-    // ! FIXME: This width is too small on retina displays. I think I need to do that think where
-    // ! you scale by some dpi scale thing. It was in the gpu_cache tutorial for rusttype.
-    start_button_transform.width = 500.0;
-    // ! FIXME: I can tell text isn't centered on retina. Didn't test on windows.
-    start_button_transform.anchor = Anchor::TopMiddle;
-    start_button_transform.position.y = 100.0;
-
-    event_loop.run(move |ev, _, control_flow| {
+    // The entities of the screen being shown, removed when switching to another screen.
+    let mut screen_entities = title_screen::setup(&mut world);
+    #[cfg(feature = "audio")]
+    title_screen::add_pad_sounds(&mut world, &screen_entities, pad_sounds);
+    let mut showing_high_scores = false;
+
+    // Set by pressing F12, a screenshot is saved after the next frame is drawn.
+    let mut take_screenshot = false;
+    // When the last frame started, to move game time by the time between frames.
+    let mut last_frame = std::time::Instant::now();
+    let mut clock = GameClock::new();
+
+    event_loop.run(move |ev, window_target, control_flow| {
         // Handle events
         match ev {
             glutin::event::Event::WindowEvent { event, .. } => match event {
@@ -65,35 +103,279 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     *control_flow = glutin::event_loop::ControlFlow::Exit;
                     return;
                 }
+                // Transforms are in logical pixels and the text system reads the scale factor
+                // every frame, so the next frame is drawn at the new scale. Keep the window's
+                // logical size, which is what winit suggests by default.
+                glutin::event::WindowEvent::ScaleFactorChanged { .. } => {}
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::F12),
+                            ..
+                        },
+                    ..
+                } => take_screenshot = true,
+                #[cfg(feature = "audio")]
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::M),
+                            ..
+                        },
+                    ..
+                } => audio_system.set_muted(!audio_system.is_muted()),
+                // Pause game time, so animations and timers freeze.
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::P),
+                            ..
+                        },
+                    ..
+                } => {
+                    if clock.is_paused() {
+                        clock.resume();
+                    } else {
+                        clock.pause();
+                    }
+                }
+                // Show the high scores, or go back to the title screen.
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::H),
+                            ..
+                        },
+                    ..
+                } => {
+                    for entity in screen_entities.drain(..) {
+                        world.remove_entity(entity);
+                    }
+                    showing_high_scores = !showing_high_scores;
+                    screen_entities = if showing_high_scores {
+                        high_score_screen::setup(&mut world, &save_data.high_scores)
+                    } else {
+                        title_screen::setup(&mut world)
+                    };
+                    #[cfg(feature = "audio")]
+                    if !showing_high_scores {
+                        title_screen::add_pad_sounds(&mut world, &screen_entities, pad_sounds);
+                    }
+                }
+                // Press the pads with 1 to 4 on the title screen.
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode:
+                                Some(
+                                    key @ (VirtualKeyCode::Key1
+                                    | VirtualKeyCode::Key2
+                                    | VirtualKeyCode::Key3
+                                    | VirtualKeyCode::Key4),
+                                ),
+                            ..
+                        },
+                    ..
+                } if !showing_high_scores => {
+                    let index = key as usize - VirtualKeyCode::Key1 as usize;
+                    title_screen::press_pad(&mut world, &screen_entities, index);
+                }
+                // Toggle fullscreen and save it for next time.
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::F11),
+                            ..
+                        },
+                    ..
+                } => {
+                    settings.window.fullscreen = match settings.window.fullscreen {
+                        FullscreenMode::Windowed => FullscreenMode::Borderless,
+                        _ => FullscreenMode::Windowed,
+                    };
+                    let gl_window = display.gl_window();
+                    let window = gl_window.window();
+                    window.set_fullscreen(settings.fullscreen(window.current_monitor()));
+                    save_data.settings = settings.clone();
+                    if let Some(path) = &save_path {
+                        if let Err(err) = save_data.save(path) {
+                            eprintln!("{}", err);
+                        }
+                    }
+                }
                 _ => {
                     input_system.process_input(&event, &mut world);
                 }
             },
 
             Event::MainEventsCleared => {
-                // Draw background
-                let mut frame = display.draw();
-                frame.clear_color(1.0, 1.0, 1.0, 0.0);
-
-                // Draw text components
-                let texts = world.borrow_component_vec::<TextComponent>().unwrap();
-                let transforms = world.borrow_component_vec::<TransformComponent>().unwrap();
-                let zip = texts.iter().zip(transforms.iter());
-                for (text, transform) in
-                    zip.filter_map(|(text, transform)| Some((text.as_ref()?, transform.as_ref()?)))
-                {
-                    text_system.draw(&mut frame, &display, text, transform);
+                let now = std::time::Instant::now();
+                clock.tick(now - last_frame);
+                last_frame = now;
+                tween_system.update(&world, clock.delta());
+                for event in timer_system.update::<TitleScreenEvent>(&world, clock.delta()) {
+                    title_screen::handle_event(&mut world, &event);
+                }
+                #[cfg(feature = "audio")]
+                audio_system.update(&world);
+
+                // Resolve text messages in the current locale
+                localization_system.update_world(&world);
+
+                let drawn = (|| {
+                    // Draw background
+                    renderer.begin_frame(settings.display.background_colour)?;
+
+                    // Draw sprites and text components by layer, so text is over the pads.
+                    render_system.draw_world(
+                        &mut renderer,
+                        &world,
+                        &mut sprite_system,
+                        &mut text_system,
+                    )?;
+
+                    // Finish drawing and swap buffers.
+                    renderer.end_frame()
+                })();
+                match drawn {
+                    Ok(()) => {}
+                    // The frame was drawn, only missing glyphs that didn't fit.
+                    Err(err @ RenderError::GlyphCacheFull { .. }) => eprintln!("{}", err),
+                    // Everything on the gpu is gone, so draw to a new window and context from
+                    // the next frame.
+                    Err(RenderError::ContextLost) => {
+                        eprintln!("the opengl context was lost, recreating the window");
+                        let recovered =
+                            create_display(&settings, window_target).and_then(|new_display| {
+                                renderer.recover(&new_display)?;
+                                display = new_display;
+                                Ok(())
+                            });
+                        if let Err(err) = recovered {
+                            eprintln!("could not recover from losing the opengl context: {}", err);
+                            print_sources(&err);
+                            *control_flow = glutin::event_loop::ControlFlow::Exit;
+                            return;
+                        }
+                    }
+                    Err(err) => {
+                        eprintln!("could not draw a frame: {}", err);
+                        print_sources(&err);
+                        *control_flow = glutin::event_loop::ControlFlow::Exit;
+                        return;
+                    }
                 }
 
-                // Finish drawing, swap buffers, consume frame.
-                frame.finish().unwrap();
+                if take_screenshot {
+                    take_screenshot = false;
+                    save_screenshot(&renderer);
+                }
             }
             _ => {}
         }
 
-        // Delay the next loop.
-        let next_frame_time =
-            std::time::Instant::now() + std::time::Duration::from_nanos(16_666_667);
-        *control_flow = glutin::event_loop::ControlFlow::WaitUntil(next_frame_time);
+        // Delay the next loop to cap the frame rate.
+        *control_flow = match settings.frame_duration() {
+            Some(frame_duration) => glutin::event_loop::ControlFlow::WaitUntil(
+                std::time::Instant::now() + frame_duration,
+            ),
+            None => glutin::event_loop::ControlFlow::Poll,
+        };
     });
 }
+
+/// Load the save file, recovering from it being broken, and give back where to save. When
+/// there's no save file yet the settings are moved into one from settings.toml, where they
+/// were saved before high scores were. A save file that couldn't be read but isn't broken,
+/// like one from a newer version of the game, isn't saved over.
+fn load_save_data(path: PathBuf) -> (SaveData, Option<PathBuf>) {
+    // Settings that can't be moved are reported, and the game starts with the defaults.
+    match SaveData::migrate_settings(&path, SETTINGS_PATH) {
+        Ok(Some(save_data)) => return (save_data, Some(path)),
+        Ok(None) => {}
+        Err(err) => {
+            eprintln!("{}, using default settings", err);
+            print_sources(&err);
+        }
+    }
+    let (save_data, err) = SaveData::load_or_recover(&path);
+    match err {
+        None => (save_data, Some(path)),
+        Some(err) if err.is_corrupt() => {
+            eprintln!("{}, loading the previous save instead", err);
+            print_sources(&err);
+            (save_data, Some(path))
+        }
+        Some(err) => {
+            eprintln!(
+                "{}, loading the previous save instead and not saving over {}",
+                err,
+                path.display()
+            );
+            print_sources(&err);
+            (save_data, None)
+        }
+    }
+}
+
+/// Make the window and its opengl context as the settings say.
+fn create_display<T>(
+    settings: &Settings,
+    window_target: &EventLoopWindowTarget<T>,
+) -> Result<Display, Error> {
+    let wb = glutin::window::WindowBuilder::new()
+        .with_inner_size(glium::glutin::dpi::LogicalSize::new(
+            settings.window.width,
+            settings.window.height,
+        ))
+        .with_fullscreen(settings.fullscreen(window_target.primary_monitor()))
+        .with_title("Rust Simon Says");
+    let cb = glutin::ContextBuilder::new().with_vsync(settings.display.vsync);
+    let gl_window = cb
+        .build_windowed(wb, window_target)
+        .map_err(DisplayCreationError::from)?;
+    let display = Display::from_gl_window(gl_window).map_err(DisplayCreationError::from)?;
+    Ok(display)
+}
+
+/// Play sound on the default device, or nowhere when there isn't one so the game still runs.
+#[cfg(feature = "audio")]
+fn audio_output() -> Box<dyn AudioOutput> {
+    #[cfg(feature = "audio-device")]
+    match ecs::system::audio::device::DeviceOutput::new() {
+        Ok(output) => return Box::new(output),
+        Err(err) => {
+            eprintln!("{}, playing without sound", err);
+            print_sources(&err);
+        }
+    }
+    Box::new(ecs::system::audio::null::NullOutput::default())
+}
+
+/// Print the chain of errors that caused an error, one per line.
+fn print_sources(err: &dyn std::error::Error) {
+    let mut source = err.source();
+    while let Some(err) = source {
+        eprintln!("  caused by: {}", err);
+        source = err.source();
+    }
+}
+
+/// Save the last frame drawn to a png in the working directory, named by the time it was taken.
+fn save_screenshot(renderer: &GliumRenderer) {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let path = format!("screenshot-{}.png", seconds);
+    match renderer.read_frame().save_png(&path) {
+        Ok(()) => println!("saved screenshot to {}", path),
+        Err(err) => eprintln!("could not save screenshot: {}", err),
+    }
+}
@@ -0,0 +1,612 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use toml::value::{Date, Datetime};
+
+use crate::settings::{Settings, SettingsError};
+
+/// The version of the save file's format. Save files from newer versions of the game aren't
+/// loaded, since they could have data this version would lose.
+pub const SAVE_VERSION: u32 = 1;
+/// How many high scores are kept.
+pub const MAX_HIGH_SCORES: usize = 10;
+
+#[derive(Debug)]
+pub enum PersistenceError {
+    /// The platform has no data directory to save in, like when HOME isn't set.
+    NoDataDir,
+    /// The save file couldn't be read from or written to disk.
+    Io(std::io::Error),
+    /// The save file isn't valid toml, or doesn't have the data it should.
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    /// The save file has no version, so it's not known how to read it.
+    MissingVersion,
+    /// The save file is from a newer version of the game.
+    UnsupportedVersion(i64),
+    Settings(SettingsError),
+    /// The settings file from before settings were saved with the high scores couldn't be
+    /// moved into the save file.
+    OldSettings(SettingsError),
+}
+
+impl fmt::Display for PersistenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PersistenceError::NoDataDir => write!(f, "there's no data directory to save in"),
+            PersistenceError::Io(err) => write!(f, "could not access save file: {}", err),
+            PersistenceError::Parse(err) => write!(f, "could not parse save file: {}", err),
+            PersistenceError::Serialize(err) => write!(f, "could not write save data: {}", err),
+            PersistenceError::MissingVersion => write!(f, "save file has no version"),
+            PersistenceError::UnsupportedVersion(version) => write!(
+                f,
+                "save file is version {}, but only up to version {} can be read",
+                version, SAVE_VERSION
+            ),
+            PersistenceError::Settings(err) => write!(f, "save file has {}", err),
+            PersistenceError::OldSettings(err) => {
+                write!(f, "could not move old settings into the save file: {}", err)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PersistenceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PersistenceError::Io(err) => Some(err),
+            PersistenceError::Parse(err) => Some(err),
+            PersistenceError::Serialize(err) => Some(err),
+            PersistenceError::Settings(err) => Some(err),
+            PersistenceError::OldSettings(err) => Some(err),
+            PersistenceError::NoDataDir
+            | PersistenceError::MissingVersion
+            | PersistenceError::UnsupportedVersion(_) => None,
+        }
+    }
+}
+
+impl PersistenceError {
+    /// Whether the save file is broken, rather than unreadable for now or from a newer
+    /// version. Only a broken save file is moved aside and saved over.
+    pub fn is_corrupt(&self) -> bool {
+        matches!(
+            self,
+            PersistenceError::Parse(_) | PersistenceError::MissingVersion
+        )
+    }
+}
+
+impl From<std::io::Error> for PersistenceError {
+    fn from(err: std::io::Error) -> Self {
+        PersistenceError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for PersistenceError {
+    fn from(err: toml::de::Error) -> Self {
+        PersistenceError::Parse(err)
+    }
+}
+
+impl From<toml::ser::Error> for PersistenceError {
+    fn from(err: toml::ser::Error) -> Self {
+        PersistenceError::Serialize(err)
+    }
+}
+
+impl From<SettingsError> for PersistenceError {
+    fn from(err: SettingsError) -> Self {
+        PersistenceError::Settings(err)
+    }
+}
+
+/// Where the game saves, like ~/.local/share/rust-simon-says/save.toml on linux.
+pub fn save_path() -> Result<PathBuf, PersistenceError> {
+    let data_dir = dirs::data_dir().ok_or(PersistenceError::NoDataDir)?;
+    Ok(data_dir.join("rust-simon-says").join("save.toml"))
+}
+
+/// How hard the game was when a score was set.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    /// The key of the difficulty's name in the localization catalogs.
+    pub fn message_key(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "difficulty-easy",
+            Difficulty::Normal => "difficulty-normal",
+            Difficulty::Hard => "difficulty-hard",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HighScore {
+    pub name: String,
+    /// The longest sequence the player repeated.
+    pub score: u32,
+    /// The day the score was set, like 2024-05-27.
+    pub date: Datetime,
+    pub difficulty: Difficulty,
+}
+
+impl HighScore {
+    /// A score set today.
+    pub fn new(name: &str, score: u32, difficulty: Difficulty) -> Self {
+        HighScore {
+            name: name.to_string(),
+            score,
+            date: today(),
+            difficulty,
+        }
+    }
+}
+
+/// The best scores, highest first. Of equal scores the one set first stays ahead.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct HighScores {
+    scores: Vec<HighScore>,
+}
+
+impl HighScores {
+    /// Whether a score would make it into the high scores.
+    pub fn qualifies(&self, score: u32) -> bool {
+        self.scores.len() < MAX_HIGH_SCORES
+            || self
+                .scores
+                .last()
+                .is_some_and(|lowest| score > lowest.score)
+    }
+
+    /// Add a score, dropping the lowest if there are too many. Returns its place from 0 for
+    /// the best, or None if it wasn't high enough.
+    pub fn add(&mut self, high_score: HighScore) -> Option<usize> {
+        let place = self
+            .scores
+            .iter()
+            .position(|other| high_score.score > other.score)
+            .unwrap_or(self.scores.len());
+        if place >= MAX_HIGH_SCORES {
+            return None;
+        }
+        self.scores.insert(place, high_score);
+        self.scores.truncate(MAX_HIGH_SCORES);
+        Some(place)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &HighScore> {
+        self.scores.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    /// Put scores from a file in order, in case it was edited by hand.
+    fn sort(&mut self) {
+        self.scores
+            .sort_by_key(|high_score| std::cmp::Reverse(high_score.score));
+        self.scores.truncate(MAX_HIGH_SCORES);
+    }
+}
+
+/// Everything the game keeps between runs, saved as toml like:
+///
+/// ```toml
+/// version = 1
+///
+/// [settings.window]
+/// width = 512
+///
+/// [[high_scores]]
+/// name = "Ada"
+/// score = 12
+/// date = 2024-05-27
+/// difficulty = "normal"
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SaveData {
+    pub version: u32,
+    pub settings: Settings,
+    pub high_scores: HighScores,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        SaveData {
+            version: SAVE_VERSION,
+            settings: Settings::default(),
+            high_scores: HighScores::default(),
+        }
+    }
+}
+
+impl SaveData {
+    /// Load a save file. A missing file isn't an error, it gives the defaults.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PersistenceError> {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(SaveData::default())
+            }
+            Err(err) => return Err(err.into()),
+        };
+        SaveData::parse(&source)
+    }
+
+    /// Load a save file, or recover from it being unreadable. The backup from before the last
+    /// save is loaded instead, or the defaults if that's unreadable too, and what went wrong
+    /// is given back. A broken save file is moved aside to save.toml.corrupt so it isn't lost.
+    /// Any other save file is left where it is, like one from a newer version of the game or
+    /// one that couldn't be read right now, so check is_corrupt before saving over it.
+    pub fn load_or_recover(path: impl AsRef<Path>) -> (Self, Option<PersistenceError>) {
+        let path = path.as_ref();
+        let err = match SaveData::load(path) {
+            Ok(save_data) => return (save_data, None),
+            Err(err) => err,
+        };
+        if err.is_corrupt() {
+            let _ = std::fs::rename(path, sibling_path(path, "corrupt"));
+        }
+        let save_data = SaveData::load(sibling_path(path, "bak")).unwrap_or_default();
+        (save_data, Some(err))
+    }
+
+    /// Settings were saved on their own in a settings file before high scores were saved. When
+    /// there's no save file yet, move the settings from that file into a new save file and
+    /// delete it, so the save file is the only place settings are kept. Gives back the new save
+    /// data, or None when there was nothing to move.
+    pub fn migrate_settings(
+        path: impl AsRef<Path>,
+        settings_path: impl AsRef<Path>,
+    ) -> Result<Option<Self>, PersistenceError> {
+        let (path, settings_path) = (path.as_ref(), settings_path.as_ref());
+        if path.exists() || !settings_path.exists() {
+            return Ok(None);
+        }
+        let save_data = SaveData {
+            settings: Settings::load(settings_path).map_err(PersistenceError::OldSettings)?,
+            ..SaveData::default()
+        };
+        save_data.save(path)?;
+        std::fs::remove_file(settings_path)?;
+        Ok(Some(save_data))
+    }
+
+    /// Parse and validate save data from toml, checking its version first.
+    pub fn parse(source: &str) -> Result<Self, PersistenceError> {
+        let version = version(source)?;
+        // Older versions would be migrated here, before being read as the current version.
+        if version < 1 || version > SAVE_VERSION as i64 {
+            return Err(PersistenceError::UnsupportedVersion(version));
+        }
+        let mut save_data: SaveData = toml::from_str(source)?;
+        save_data.settings.validate()?;
+        save_data.high_scores.sort();
+        Ok(save_data)
+    }
+
+    /// Save to a file, making its directory if needed. The data is written to a temporary
+    /// file that then replaces the old one, so the save file is never left half written, and
+    /// the old one is kept as save.toml.bak. A save file from a newer version of the game is
+    /// never saved over.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), PersistenceError> {
+        let path = path.as_ref();
+        self.settings.validate()?;
+        if let Ok(source) = std::fs::read_to_string(path) {
+            match version(&source) {
+                Ok(version) if version > SAVE_VERSION as i64 => {
+                    return Err(PersistenceError::UnsupportedVersion(version))
+                }
+                _ => {}
+            }
+        }
+        let source = toml::to_string(&SaveData {
+            version: SAVE_VERSION,
+            ..self.clone()
+        })?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let temporary_path = sibling_path(path, "tmp");
+        let mut file = File::create(&temporary_path)?;
+        file.write_all(source.as_bytes())?;
+        file.sync_all()?;
+        if path.exists() {
+            std::fs::copy(path, sibling_path(path, "bak"))?;
+        }
+        std::fs::rename(&temporary_path, path)?;
+        Ok(())
+    }
+}
+
+/// The version of save data in toml.
+fn version(source: &str) -> Result<i64, PersistenceError> {
+    let table: toml::Table = toml::from_str(source)?;
+    table
+        .get("version")
+        .and_then(|version| version.as_integer())
+        .ok_or(PersistenceError::MissingVersion)
+}
+
+/// The path with another extension added, like save.toml.bak.
+fn sibling_path(path: &Path, extension: &str) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".");
+    file_name.push(extension);
+    path.with_file_name(file_name)
+}
+
+/// Today's date in UTC.
+fn today() -> Datetime {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    Datetime {
+        date: Some(Date {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+        }),
+        time: None,
+        offset: None,
+    }
+}
+
+/// The year, month, and day of a number of days since 1970-01-01, from Howard Hinnant's
+/// date algorithms.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::FullscreenMode;
+
+    fn date(year: u16, month: u8, day: u8) -> Datetime {
+        Datetime {
+            date: Some(Date { year, month, day }),
+            time: None,
+            offset: None,
+        }
+    }
+
+    fn high_score(name: &str, score: u32) -> HighScore {
+        HighScore {
+            name: name.to_string(),
+            score,
+            date: date(2024, 5, 27),
+            difficulty: Difficulty::Normal,
+        }
+    }
+
+    fn names(high_scores: &HighScores) -> Vec<&str> {
+        high_scores
+            .iter()
+            .map(|high_score| high_score.name.as_str())
+            .collect()
+    }
+
+    fn save_data() -> SaveData {
+        let mut save_data = SaveData::default();
+        save_data.settings.window.width = 800;
+        save_data.settings.window.fullscreen = FullscreenMode::Borderless;
+        save_data.high_scores.add(high_score("Ada", 12));
+        save_data.high_scores.add(HighScore {
+            difficulty: Difficulty::Hard,
+            ..high_score("Grace", 9)
+        });
+        save_data
+    }
+
+    #[test]
+    fn save_data_parses_back_the_same() {
+        let save_data = save_data();
+        let source = toml::to_string(&save_data).unwrap();
+        assert_eq!(SaveData::parse(&source).unwrap(), save_data);
+    }
+
+    #[test]
+    fn save_data_loads_back_the_same_from_a_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("saves").join("save.toml");
+        save_data().save(&path).unwrap();
+        assert_eq!(SaveData::load(&path).unwrap(), save_data());
+    }
+
+    #[test]
+    fn a_missing_save_file_loads_the_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let save_data = SaveData::load(dir.path().join("save.toml")).unwrap();
+        assert_eq!(save_data, SaveData::default());
+    }
+
+    #[test]
+    fn a_save_file_from_a_newer_version_is_rejected() {
+        let source = format!("version = {}", SAVE_VERSION + 1);
+        assert!(matches!(
+            SaveData::parse(&source),
+            Err(PersistenceError::UnsupportedVersion(version)) if version == SAVE_VERSION as i64 + 1
+        ));
+        assert!(matches!(
+            SaveData::parse("[settings.window]\nwidth = 800"),
+            Err(PersistenceError::MissingVersion)
+        ));
+    }
+
+    #[test]
+    fn high_scores_are_kept_highest_first_with_ties_in_the_order_set() {
+        let mut high_scores = HighScores::default();
+        assert_eq!(high_scores.add(high_score("Ada", 5)), Some(0));
+        assert_eq!(high_scores.add(high_score("Grace", 9)), Some(0));
+        assert_eq!(high_scores.add(high_score("Alan", 5)), Some(2));
+        assert_eq!(high_scores.add(high_score("Edsger", 7)), Some(1));
+        assert_eq!(names(&high_scores), ["Grace", "Edsger", "Ada", "Alan"]);
+    }
+
+    #[test]
+    fn only_the_best_high_scores_are_kept() {
+        let mut high_scores = HighScores::default();
+        for score in 1..=MAX_HIGH_SCORES as u32 {
+            assert!(high_scores.qualifies(score));
+            high_scores.add(high_score(&score.to_string(), score));
+        }
+        assert_eq!(high_scores.len(), MAX_HIGH_SCORES);
+        // A score has to beat the lowest to get in once the list is full.
+        assert!(!high_scores.qualifies(1));
+        assert_eq!(high_scores.add(high_score("tie", 1)), None);
+        assert!(high_scores.qualifies(2));
+        assert_eq!(high_scores.add(high_score("best", 100)), Some(0));
+        assert_eq!(high_scores.len(), MAX_HIGH_SCORES);
+        assert_eq!(high_scores.iter().next().unwrap().name, "best");
+        assert_eq!(high_scores.iter().last().unwrap().score, 2);
+    }
+
+    #[test]
+    fn hand_edited_high_scores_are_put_in_order() {
+        let source = r#"
+            version = 1
+
+            [[high_scores]]
+            name = "Ada"
+            score = 3
+            date = 2024-05-27
+            difficulty = "easy"
+
+            [[high_scores]]
+            name = "Grace"
+            score = 8
+            date = 2024-05-28
+            difficulty = "hard"
+        "#;
+        let save_data = SaveData::parse(source).unwrap();
+        assert_eq!(names(&save_data.high_scores), ["Grace", "Ada"]);
+    }
+
+    #[test]
+    fn a_corrupt_save_file_is_moved_aside_and_the_backup_loaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("save.toml");
+        // The second save keeps the first as the backup.
+        save_data().save(&path).unwrap();
+        SaveData::default().save(&path).unwrap();
+        std::fs::write(&path, "version = 1\nhigh_scores = [[[").unwrap();
+
+        let (loaded, err) = SaveData::load_or_recover(&path);
+        assert!(err.is_some_and(|err| err.is_corrupt()));
+        assert_eq!(loaded, save_data());
+        assert!(!path.exists());
+        assert!(dir.path().join("save.toml.corrupt").exists());
+    }
+
+    #[test]
+    fn a_corrupt_save_file_without_a_backup_loads_the_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("save.toml");
+        std::fs::write(&path, "width = 800").unwrap();
+
+        let (save_data, err) = SaveData::load_or_recover(&path);
+        assert!(matches!(err, Some(PersistenceError::MissingVersion)));
+        assert_eq!(save_data, SaveData::default());
+        assert!(dir.path().join("save.toml.corrupt").exists());
+    }
+
+    #[test]
+    fn a_save_file_from_a_newer_version_is_left_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("save.toml");
+        let source = format!("version = {}\nachievements = []", SAVE_VERSION + 1);
+        std::fs::write(&path, &source).unwrap();
+
+        let (save_data, err) = SaveData::load_or_recover(&path);
+        assert!(err.is_some_and(|err| !err.is_corrupt()));
+        assert_eq!(save_data, SaveData::default());
+        assert!(matches!(
+            SaveData::default().save(&path),
+            Err(PersistenceError::UnsupportedVersion(_))
+        ));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), source);
+        assert!(!dir.path().join("save.toml.corrupt").exists());
+    }
+
+    #[test]
+    fn a_save_file_that_cant_be_read_is_left_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        // Reading a directory fails, like a file that can't be read right now.
+        let path = dir.path().join("save.toml");
+        std::fs::create_dir(&path).unwrap();
+
+        let (_, err) = SaveData::load_or_recover(&path);
+        assert!(matches!(err, Some(PersistenceError::Io(_))));
+        assert!(path.is_dir());
+        assert!(!dir.path().join("save.toml.corrupt").exists());
+    }
+
+    #[test]
+    fn old_settings_are_moved_into_a_new_save_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("save.toml");
+        let settings_path = dir.path().join("settings.toml");
+        std::fs::write(&settings_path, "[window]\nwidth = 800").unwrap();
+
+        let save_data = SaveData::migrate_settings(&path, &settings_path)
+            .unwrap()
+            .unwrap();
+        assert_eq!(save_data.settings.window.width, 800);
+        assert_eq!(SaveData::load(&path).unwrap(), save_data);
+        assert!(!settings_path.exists());
+        // There's nothing to move the next time.
+        assert!(SaveData::migrate_settings(&path, &settings_path)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn old_settings_dont_replace_a_save_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("save.toml");
+        let settings_path = dir.path().join("settings.toml");
+        save_data().save(&path).unwrap();
+        std::fs::write(&settings_path, "[window]\nwidth = 300").unwrap();
+
+        assert!(SaveData::migrate_settings(&path, &settings_path)
+            .unwrap()
+            .is_none());
+        assert_eq!(SaveData::load(&path).unwrap(), save_data());
+    }
+}
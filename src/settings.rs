@@ -0,0 +1,332 @@
+use glium::glutin::monitor::MonitorHandle;
+use glium::glutin::window::Fullscreen;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+/// Where settings were saved, relative to the working directory, before they were kept in the
+/// save file with the high scores. Only read to move them into the save file.
+pub const SETTINGS_PATH: &str = "settings.toml";
+
+#[derive(Debug)]
+pub enum SettingsError {
+    /// The settings file couldn't be read from disk.
+    Io(std::io::Error),
+    /// The file isn't valid toml, or has a setting of the wrong type or that doesn't exist.
+    Parse(toml::de::Error),
+    /// A setting has a value outside what it allows.
+    Invalid {
+        setting: &'static str,
+        reason: String,
+    },
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(err) => write!(f, "could not access settings file: {}", err),
+            SettingsError::Parse(err) => write!(f, "could not parse settings file: {}", err),
+            SettingsError::Invalid { setting, reason } => {
+                write!(f, "invalid setting `{}`: {}", setting, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SettingsError::Io(err) => Some(err),
+            SettingsError::Parse(err) => Some(err),
+            SettingsError::Invalid { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for SettingsError {
+    fn from(err: std::io::Error) -> Self {
+        SettingsError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for SettingsError {
+    fn from(err: toml::de::Error) -> Self {
+        SettingsError::Parse(err)
+    }
+}
+
+/// How the window covers the screen.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FullscreenMode {
+    #[default]
+    Windowed,
+    /// A window without decorations covering the whole monitor it's on.
+    Borderless,
+    /// Change the monitor's video mode to the largest one it has.
+    Exclusive,
+}
+
+/// Window and display settings, like:
+///
+/// ```toml
+/// [window]
+/// width = 512
+/// height = 512
+/// fullscreen = "borderless"
+///
+/// [display]
+/// vsync = true
+/// msaa = 4
+/// background_colour = [1.0, 1.0, 1.0, 1.0]
+/// frame_rate_cap = 60
+/// ```
+///
+/// Settings missing from the file keep their defaults.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub window: WindowSettings,
+    pub display: DisplaySettings,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowSettings {
+    /// Size of the window in logical pixels when it isn't fullscreen.
+    pub width: u32,
+    pub height: u32,
+    pub fullscreen: FullscreenMode,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        WindowSettings {
+            width: 512,
+            height: 512,
+            fullscreen: FullscreenMode::Windowed,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplaySettings {
+    /// Wait for the monitor's refresh before showing a frame. Only read when the window is made.
+    pub vsync: bool,
+    /// Samples per pixel for multisample antialiasing, 0 to turn it off.
+    pub msaa: u32,
+    pub background_colour: [f32; 4],
+    /// Most frames drawn per second, 0 for no limit.
+    pub frame_rate_cap: u32,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        DisplaySettings {
+            vsync: true,
+            msaa: 0,
+            background_colour: [1.0, 1.0, 1.0, 1.0],
+            frame_rate_cap: 60,
+        }
+    }
+}
+
+const MIN_WINDOW_SIZE: u32 = 100;
+const MAX_WINDOW_SIZE: u32 = 16384;
+const MSAA_SAMPLES: [u32; 5] = [0, 2, 4, 8, 16];
+const MAX_FRAME_RATE_CAP: u32 = 1000;
+
+impl Settings {
+    /// Load settings from a toml file. A missing file isn't an error, it gives the defaults.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SettingsError> {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Settings::default())
+            }
+            Err(err) => return Err(err.into()),
+        };
+        Settings::parse(&source)
+    }
+
+    /// Parse and validate settings from toml.
+    pub fn parse(source: &str) -> Result<Self, SettingsError> {
+        let settings: Settings = toml::from_str(source)?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Check each setting is in the range it allows.
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        let window_sizes = [
+            ("window.width", self.window.width),
+            ("window.height", self.window.height),
+        ];
+        for (setting, size) in window_sizes {
+            if !(MIN_WINDOW_SIZE..=MAX_WINDOW_SIZE).contains(&size) {
+                return Err(SettingsError::Invalid {
+                    setting,
+                    reason: format!(
+                        "{} is not between {} and {}",
+                        size, MIN_WINDOW_SIZE, MAX_WINDOW_SIZE
+                    ),
+                });
+            }
+        }
+        if !MSAA_SAMPLES.contains(&self.display.msaa) {
+            return Err(SettingsError::Invalid {
+                setting: "display.msaa",
+                reason: format!("{} is not one of {:?}", self.display.msaa, MSAA_SAMPLES),
+            });
+        }
+        let colour = self.display.background_colour;
+        if !colour.iter().all(|channel| (0.0..=1.0).contains(channel)) {
+            return Err(SettingsError::Invalid {
+                setting: "display.background_colour",
+                reason: format!("{:?} has a channel that's not between 0 and 1", colour),
+            });
+        }
+        if self.display.frame_rate_cap > MAX_FRAME_RATE_CAP {
+            return Err(SettingsError::Invalid {
+                setting: "display.frame_rate_cap",
+                reason: format!(
+                    "{} is more than {}, use 0 for no limit",
+                    self.display.frame_rate_cap, MAX_FRAME_RATE_CAP
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// The shortest time between frames, or None when the frame rate isn't capped.
+    pub fn frame_duration(&self) -> Option<Duration> {
+        match self.display.frame_rate_cap {
+            0 => None,
+            cap => Some(Duration::from_secs(1) / cap),
+        }
+    }
+
+    /// The window's fullscreen state for the fullscreen mode, on a monitor. Exclusive
+    /// fullscreen falls back to borderless if the monitor has no video modes.
+    pub fn fullscreen(&self, monitor: Option<MonitorHandle>) -> Option<Fullscreen> {
+        match self.window.fullscreen {
+            FullscreenMode::Windowed => None,
+            FullscreenMode::Borderless => Some(Fullscreen::Borderless(monitor)),
+            FullscreenMode::Exclusive => {
+                let video_mode = monitor.as_ref().and_then(|monitor| {
+                    monitor.video_modes().max_by_key(|mode| {
+                        let size = mode.size();
+                        (size.width * size.height, mode.refresh_rate())
+                    })
+                });
+                match video_mode {
+                    Some(video_mode) => Some(Fullscreen::Exclusive(video_mode)),
+                    None => Some(Fullscreen::Borderless(monitor)),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The setting parsing the toml was rejected for.
+    fn invalid_setting(source: &str) -> &'static str {
+        match Settings::parse(source) {
+            Err(SettingsError::Invalid { setting, .. }) => setting,
+            other => panic!("expected an invalid setting, got {:?}", other),
+        }
+    }
+
+    fn assert_parse_error(source: &str) {
+        let result = Settings::parse(source);
+        assert!(
+            matches!(result, Err(SettingsError::Parse(_))),
+            "{:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn an_empty_file_gives_the_defaults() {
+        assert_eq!(Settings::parse("").unwrap(), Settings::default());
+    }
+
+    #[test]
+    fn settings_missing_from_the_file_keep_their_defaults() {
+        let settings = Settings::parse("[window]\nwidth = 800\n\n[display]\nmsaa = 4\n").unwrap();
+        assert_eq!(settings.window.width, 800);
+        assert_eq!(settings.window.height, WindowSettings::default().height);
+        assert_eq!(settings.window.fullscreen, FullscreenMode::Windowed);
+        assert_eq!(settings.display.msaa, 4);
+        assert_eq!(settings.display.frame_rate_cap, 60);
+        assert!(settings.display.vsync);
+    }
+
+    #[test]
+    fn settings_round_trip_through_toml() {
+        let mut settings = Settings::default();
+        settings.window.fullscreen = FullscreenMode::Borderless;
+        settings.display.background_colour = [0.5, 0.25, 0.0, 1.0];
+        let source = toml::to_string(&settings).unwrap();
+        assert_eq!(Settings::parse(&source).unwrap(), settings);
+    }
+
+    #[test]
+    fn window_sizes_out_of_range_are_invalid() {
+        assert_eq!(invalid_setting("[window]\nwidth = 99\n"), "window.width");
+        assert_eq!(
+            invalid_setting("[window]\nheight = 16385\n"),
+            "window.height"
+        );
+        assert!(Settings::parse("[window]\nwidth = 100\nheight = 16384\n").is_ok());
+    }
+
+    #[test]
+    fn msaa_must_be_a_supported_sample_count() {
+        assert_eq!(invalid_setting("[display]\nmsaa = 3\n"), "display.msaa");
+        assert!(Settings::parse("[display]\nmsaa = 16\n").is_ok());
+    }
+
+    #[test]
+    fn background_colour_channels_must_be_between_0_and_1() {
+        let source = "[display]\nbackground_colour = [1.0, 1.5, 1.0, 1.0]\n";
+        assert_eq!(invalid_setting(source), "display.background_colour");
+        let source = "[display]\nbackground_colour = [1.0, 1.0, -0.1, 1.0]\n";
+        assert_eq!(invalid_setting(source), "display.background_colour");
+    }
+
+    #[test]
+    fn frame_rate_cap_can_be_at_most_1000() {
+        let source = "[display]\nframe_rate_cap = 1001\n";
+        assert_eq!(invalid_setting(source), "display.frame_rate_cap");
+        let settings = Settings::parse("[display]\nframe_rate_cap = 0\n").unwrap();
+        assert_eq!(settings.frame_duration(), None);
+        let settings = Settings::parse("[display]\nframe_rate_cap = 1000\n").unwrap();
+        assert_eq!(settings.frame_duration(), Some(Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn unknown_settings_are_rejected() {
+        assert_parse_error("[window]\nwidht = 800\n");
+        assert_parse_error("[audio]\nvolume = 1.0\n");
+    }
+
+    #[test]
+    fn settings_of_the_wrong_type_are_rejected() {
+        assert_parse_error("[window]\nwidth = \"wide\"\n");
+        assert_parse_error("[window]\nfullscreen = \"sideways\"\n");
+        assert_parse_error("[display]\nvsync = 1\n");
+    }
+
+    #[test]
+    fn a_missing_file_gives_the_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = Settings::load(dir.path().join(SETTINGS_PATH)).unwrap();
+        assert_eq!(settings, Settings::default());
+    }
+}
@@ -0,0 +1,335 @@
+use std::time::Duration;
+
+use crate::ecs::component::sprite::{Shape, SpriteComponent};
+use crate::ecs::component::text::{TextAlignment, TextComponent, TextMessage};
+use crate::ecs::component::timer::{Timer, TimerComponent};
+use crate::ecs::component::transform::{Anchor, TransformComponent};
+use crate::ecs::component::tween::{Easing, Looping, Tween, TweenComponent, TweenProperty};
+use crate::ecs::system::timer::TimerEvent;
+use crate::ecs::World;
+
+#[cfg(feature = "audio")]
+use crate::ecs::component::audio_source::AudioSourceComponent;
+#[cfg(feature = "audio")]
+use crate::ecs::system::audio::synth::{Patch, SIMON_PAD_FREQUENCIES};
+#[cfg(feature = "audio")]
+use crate::ecs::system::audio::{AudioOutput, AudioSystem, SoundHandle};
+
+/// The simon pads' colours: green, red, yellow, and blue.
+const PAD_COLOURS: [[f32; 4]; 4] = [
+    [0.1, 0.7, 0.2, 1.0],
+    [0.85, 0.15, 0.15, 1.0],
+    [0.95, 0.8, 0.1, 1.0],
+    [0.15, 0.35, 0.85, 1.0],
+];
+/// How long each pad waits for the one before it while the pads light up in turn.
+const PAD_STEP: Duration = Duration::from_millis(700);
+/// How long a pad stays lit, fading up and back down.
+const PAD_LIGHT: Duration = Duration::from_millis(250);
+
+/// Fired by the title screen's timers. Give them to handle_event.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TitleScreenEvent {
+    /// Light up one of the pads, then the next after PAD_STEP, like a demo of the game.
+    LightPad { pads: [usize; 4], index: usize },
+}
+
+/// Add the title screen's entities to the world: the title, the start button, and the four
+/// simon pads. Text uses message keys, so a LocalizationSystem has to fill it in. The pads
+/// light up in turn on TimerComponent<TitleScreenEvent> timers. Returns the entities it made,
+/// to remove when leaving the screen.
+pub fn setup(world: &mut World) -> Vec<usize> {
+    // Setup title
+    let title_entity = world.new_entity();
+    world.add_component_to_entity(
+        title_entity,
+        TextComponent {
+            message: Some(TextMessage::new("title")),
+            alignment: TextAlignment::Center,
+            ..Default::default()
+        },
+    );
+    let title_transform = world
+        .borrow_component::<TransformComponent>(title_entity)
+        .unwrap();
+    title_transform.width = 300.0;
+    title_transform.anchor = Anchor::TopMiddle;
+    title_transform.position.y = 20.0;
+
+    // Setup subtitle
+    let start_button = world.new_entity();
+    world.add_component_to_entity(
+        start_button,
+        TextComponent {
+            alignment: TextAlignment::Center,
+            message: Some(TextMessage::new("press-enter-to-play")),
+            ..Default::default()
+        },
+    );
+    let start_button_transform = world
+        .borrow_component::<TransformComponent>(start_button)
+        .unwrap();
+    start_button_transform.width = 500.0;
+    start_button_transform.anchor = Anchor::TopMiddle;
+    start_button_transform.position.y = 100.0;
+    // Pulse the start button so it's noticed.
+    world.add_component_to_entity(
+        start_button,
+        TweenComponent::new(
+            Tween::new(
+                TweenProperty::TextColour {
+                    from: [0.0, 0.0, 0.0, 1.0],
+                    to: [0.0, 0.0, 0.0, 0.35],
+                },
+                Duration::from_millis(800),
+            )
+            .easing(Easing::EaseInOut)
+            .looping(Looping::Forever)
+            .yoyo(),
+        ),
+    );
+
+    // Setup the four simon pads in a square under the text.
+    let mut pads = [0; 4];
+    for (i, colour) in PAD_COLOURS.into_iter().enumerate() {
+        let pad = world.new_entity();
+        pads[i] = pad;
+        world.add_component_to_entity(
+            pad,
+            SpriteComponent {
+                shape: Shape::RoundedRectangle { radius: 16.0 },
+                colour,
+                ..Default::default()
+            },
+        );
+        let pad_transform = world.borrow_component::<TransformComponent>(pad).unwrap();
+        pad_transform.width = 150.0;
+        pad_transform.height = 150.0;
+        pad_transform.anchor = Anchor::TopMiddle;
+        pad_transform.position.x = if i % 2 == 0 { -80.0 } else { 80.0 };
+        pad_transform.position.y = if i < 2 { 170.0 } else { 330.0 };
+    }
+    // Start lighting up the pads.
+    world.add_component_to_entity(
+        title_entity,
+        TimerComponent::new(Timer::once(
+            PAD_STEP,
+            TitleScreenEvent::LightPad { pads, index: 0 },
+        )),
+    );
+
+    let mut entities = vec![title_entity, start_button];
+    entities.extend(pads);
+    entities
+}
+
+/// Handle an event from one of the title screen's timers.
+pub fn handle_event(world: &mut World, event: &TimerEvent<TitleScreenEvent>) {
+    match event.event {
+        TitleScreenEvent::LightPad { pads, index } => {
+            light_pad(world, pads[index], PAD_COLOURS[index]);
+            // The timer that fired is removed, so the next pad gets a new one.
+            let next = TitleScreenEvent::LightPad {
+                pads,
+                index: (index + 1) % pads.len(),
+            };
+            if let Some(timers) =
+                world.borrow_component::<TimerComponent<TitleScreenEvent>>(event.entity)
+            {
+                timers.timers.push(Timer::once(PAD_STEP, next));
+            }
+        }
+    }
+}
+
+/// Light up a pad as if it was pressed, by its index from 0 to 3 in the order of PAD_COLOURS.
+/// The entities are the ones setup made.
+pub fn press_pad(world: &mut World, entities: &[usize], index: usize) {
+    if let Some(&pad) = pads(world, entities).get(index) {
+        light_pad(world, pad, PAD_COLOURS[index]);
+    }
+}
+
+/// Load each pad's tone, in the order of PAD_COLOURS.
+#[cfg(feature = "audio")]
+pub fn load_pad_sounds<O: AudioOutput>(audio_system: &mut AudioSystem<O>) -> [SoundHandle; 4] {
+    SIMON_PAD_FREQUENCIES
+        .map(|frequency| audio_system.load_patch(&Patch::simon_pad(frequency), PAD_LIGHT * 2))
+}
+
+/// Give the pads sounds to play each time they light up, like from load_pad_sounds. The
+/// entities are the ones setup made.
+#[cfg(feature = "audio")]
+pub fn add_pad_sounds(world: &mut World, entities: &[usize], sounds: [SoundHandle; 4]) {
+    for (pad, sound) in pads(world, entities).into_iter().zip(sounds) {
+        world.add_component_to_entity(pad, AudioSourceComponent::new(sound));
+    }
+}
+
+/// The pads among the screen's entities, the ones with sprites.
+fn pads(world: &World, entities: &[usize]) -> Vec<usize> {
+    let Some(sprites) = world.borrow_component_vec::<SpriteComponent>() else {
+        return Vec::new();
+    };
+    entities
+        .iter()
+        .copied()
+        .filter(|&entity| sprites[entity].is_some())
+        .collect()
+}
+
+/// Flash a pad towards white and back, and play its sound if it has one.
+fn light_pad(world: &mut World, pad: usize, colour: [f32; 4]) {
+    let lit = [
+        colour[0] * 0.5 + 0.5,
+        colour[1] * 0.5 + 0.5,
+        colour[2] * 0.5 + 0.5,
+        colour[3],
+    ];
+    world.add_component_to_entity(
+        pad,
+        TweenComponent::new(
+            Tween::new(
+                TweenProperty::SpriteColour {
+                    from: colour,
+                    to: lit,
+                },
+                PAD_LIGHT,
+            )
+            .easing(Easing::EaseOut)
+            .looping(Looping::Times(2))
+            .yoyo(),
+        ),
+    );
+    #[cfg(feature = "audio")]
+    if let Some(audio_source) = world.borrow_component::<AudioSourceComponent>(pad) {
+        audio_source.trigger();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::system::timer::TimerSystem;
+    use crate::ecs::system::tween::TweenSystem;
+
+    /// Run the title screen's tweens and timers forward like the game loop does.
+    fn update(world: &mut World, systems: &mut (TweenSystem, TimerSystem), delta: Duration) {
+        systems.0.update(world, delta);
+        for event in systems.1.update::<TitleScreenEvent>(world, delta) {
+            handle_event(world, &event);
+        }
+    }
+
+    /// Whether a pad is flashing, with a tween that hasn't finished yet.
+    fn is_lit(world: &mut World, pad: usize) -> bool {
+        world
+            .borrow_component::<TweenComponent>(pad)
+            .is_some_and(|tween| !tween.tweens.is_empty())
+    }
+
+    fn colour(world: &mut World, pad: usize) -> [f32; 4] {
+        world
+            .borrow_component::<SpriteComponent>(pad)
+            .unwrap()
+            .colour
+    }
+
+    fn assert_colour(world: &mut World, pad: usize, expected: [f32; 4]) {
+        let colour = colour(world, pad);
+        assert!(
+            colour
+                .iter()
+                .zip(expected)
+                .all(|(a, b)| (a - b).abs() < 0.001),
+            "{:?} != {:?}",
+            colour,
+            expected
+        );
+    }
+
+    #[test]
+    fn the_pads_light_up_in_turn() {
+        let mut world = World::new();
+        setup(&mut world);
+        // The pads are the entities after the title and start button.
+        let pads = [2, 3, 4, 5];
+        let mut systems = (TweenSystem::new(), TimerSystem::new());
+        assert!(!pads.iter().any(|&pad| is_lit(&mut world, pad)));
+
+        for (step, &pad) in pads.iter().enumerate() {
+            update(&mut world, &mut systems, PAD_STEP);
+            // Only the pad for this step is lit, the last one has gone back to its colour.
+            for (i, &other) in pads.iter().enumerate() {
+                assert_eq!(
+                    is_lit(&mut world, other),
+                    other == pad,
+                    "pad {} at step {}",
+                    other,
+                    step
+                );
+                if other != pad {
+                    assert_colour(&mut world, other, PAD_COLOURS[i]);
+                }
+            }
+        }
+        // Then it starts again from the first pad.
+        let timers = world
+            .borrow_component::<TimerComponent<TitleScreenEvent>>(0)
+            .unwrap();
+        assert_eq!(
+            timers.timers,
+            vec![Timer::once(
+                PAD_STEP,
+                TitleScreenEvent::LightPad { pads, index: 0 }
+            )]
+        );
+    }
+
+    #[test]
+    fn a_lit_pad_flashes_towards_white_and_back() {
+        let mut world = World::new();
+        let entities = setup(&mut world);
+        let mut tween_system = TweenSystem::new();
+        press_pad(&mut world, &entities, 2);
+        assert!(is_lit(&mut world, 4));
+        assert!(!is_lit(&mut world, 2));
+
+        let [r, g, b, a] = PAD_COLOURS[2];
+        tween_system.update(&world, PAD_LIGHT);
+        assert_colour(
+            &mut world,
+            4,
+            [r * 0.5 + 0.5, g * 0.5 + 0.5, b * 0.5 + 0.5, a],
+        );
+        tween_system.update(&world, PAD_LIGHT);
+        assert_colour(&mut world, 4, PAD_COLOURS[2]);
+        assert!(!is_lit(&mut world, 4));
+    }
+
+    #[cfg(feature = "audio")]
+    #[test]
+    fn a_pad_plays_its_tone_when_it_lights_up() {
+        use crate::ecs::system::audio::null::RecordingOutput;
+
+        let mut world = World::new();
+        let entities = setup(&mut world);
+        let mut audio_system = AudioSystem::new(RecordingOutput::default());
+        let sounds = load_pad_sounds(&mut audio_system);
+        add_pad_sounds(&mut world, &entities, sounds);
+        let mut systems = (TweenSystem::new(), TimerSystem::new());
+
+        update(&mut world, &mut systems, PAD_STEP);
+        press_pad(&mut world, &entities, 3);
+        audio_system.update(&world);
+        let played = audio_system.output_mut().take_played();
+        // The first pad from the timer, then the pressed one, in entity order.
+        assert_eq!(played.len(), 2);
+        let tone = |index: usize| {
+            let patch = Patch::simon_pad(SIMON_PAD_FREQUENCIES[index]);
+            patch.render(PAD_LIGHT * 2, 44100).samples
+        };
+        assert_eq!(*played[0].samples, tone(0));
+        assert_eq!(*played[1].samples, tone(3));
+    }
+}
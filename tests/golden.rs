@@ -0,0 +1,99 @@
+//! Draw the title screen offscreen and compare it with golden/title_screen.png, so changes to
+//! how things are drawn are noticed. Works without a window, like on CI with mesa's llvmpipe,
+//! and is skipped when no opengl context can be made without one. Text is placed a little
+//! differently when it's shaped by rustybuzz, so the shaping feature has its own image,
+//! golden/title_screen_shaping.png.
+//!
+//! Set UPDATE_GOLDEN to save the current drawing as the new golden image after checking it by
+//! eye:
+//!
+//!     UPDATE_GOLDEN=1 cargo test --test golden
+//!     UPDATE_GOLDEN=1 cargo test --test golden --features shaping
+//!
+//! When the images differ the drawing is saved to target/golden with the same name.
+#![cfg(target_os = "linux")]
+
+use rust_simon_says::ecs;
+use rust_simon_says::title_screen;
+
+use ecs::component::transform::Viewport;
+use ecs::system::localization::LocalizationSystem;
+use ecs::system::render::glium_renderer::GliumRenderer;
+use ecs::system::render::headless::headless_context;
+use ecs::system::render::image::RgbaImage;
+use ecs::system::render::{RenderSystem, Renderer};
+use ecs::system::sprite::SpriteSystem;
+use ecs::system::text::TextSystem;
+use rust_simon_says::settings::Settings;
+use std::path::Path;
+
+/// How far a channel can be off before a pixel counts as different, for differences in how
+/// drivers antialias and blend.
+const CHANNEL_TOLERANCE: u8 = 8;
+/// How many pixels can be different, for drivers that rasterize edges a little differently.
+/// A single 24px glyph drawn a pixel off changes 50 to 130 pixels, so even that is caught.
+const MAX_DIFFERENT_PIXELS: usize = 40;
+
+#[cfg(not(feature = "shaping"))]
+const GOLDEN_IMAGE: &str = "title_screen.png";
+#[cfg(feature = "shaping")]
+const GOLDEN_IMAGE: &str = "title_screen_shaping.png";
+
+#[test]
+fn title_screen_matches_golden_image() -> Result<(), Box<dyn std::error::Error>> {
+    let context = match headless_context() {
+        Ok(context) => context,
+        Err(err) => {
+            eprintln!("skipping, {}", err);
+            return Ok(());
+        }
+    };
+
+    // The default window's size, always in english so the image doesn't depend on LANG.
+    let viewport = Viewport {
+        width: 512.0,
+        height: 512.0,
+        scale_factor: 1.0,
+    };
+    let mut renderer = GliumRenderer::headless(context, viewport)?;
+    let mut text_system = TextSystem::new()?;
+    let mut sprite_system = SpriteSystem::new();
+    let mut render_system = RenderSystem::new();
+    let mut localization_system = LocalizationSystem::new("en");
+    localization_system.load_builtin_catalogs()?;
+
+    let mut world = ecs::World::new();
+    title_screen::setup(&mut world);
+    localization_system.update_world(&world);
+
+    renderer.begin_frame(Settings::default().display.background_colour)?;
+    render_system.draw_world(&mut renderer, &world, &mut sprite_system, &mut text_system)?;
+    renderer.end_frame()?;
+    let image = renderer.read_frame();
+
+    let golden_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("golden")
+        .join(GOLDEN_IMAGE);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        image.save_png(&golden_path)?;
+        eprintln!("updated {}", golden_path.display());
+        return Ok(());
+    }
+
+    let golden = RgbaImage::load_png(&golden_path)?;
+    let difference = image.compare(&golden, CHANNEL_TOLERANCE);
+    if !difference.matches(MAX_DIFFERENT_PIXELS) {
+        let actual_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("target/golden")
+            .join(GOLDEN_IMAGE);
+        std::fs::create_dir_all(actual_path.parent().unwrap())?;
+        image.save_png(&actual_path)?;
+        panic!(
+            "title screen doesn't match {}: {:?}, drawn image saved to {}",
+            golden_path.display(),
+            difference,
+            actual_path.display()
+        );
+    }
+    Ok(())
+}